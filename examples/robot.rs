@@ -0,0 +1,24 @@
+/// Load a robot config, check every joint is on the bus, and print where they are.
+///
+/// Run with `cargo run --features config --example robot -- examples/robot.toml`.
+extern crate waveshare_serial_servo;
+
+use std::env;
+
+use waveshare_serial_servo::config::RobotConfig;
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "examples/robot.toml".into());
+
+    let config = RobotConfig::load(&path).expect("Config must be valid.");
+    let mut robot = config.connect().expect("Every joint must answer.");
+
+    for (name, joint) in &robot.joints {
+        match joint.read_angle(&mut robot.port) {
+            Ok(angle) => println!("{name}: {:.1} degrees", angle.0),
+            Err(e) => println!("{name}: {e}"),
+        }
+    }
+}
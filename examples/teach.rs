@@ -0,0 +1,52 @@
+/// Record a hand-posed motion, save it to a file, and replay it.
+///
+extern crate waveshare_serial_servo;
+
+#[path = "./common/lib.rs"]
+mod common;
+
+use std::{
+    fs::File,
+    io::{stdin, BufReader},
+    time::Duration,
+};
+
+use waveshare_serial_servo::{
+    servo::Servo,
+    teach::{self, Recording},
+};
+
+fn main() {
+    let mut port = common::get_port();
+
+    println!("How many servos?");
+    let count = common::ask::<usize, _>(|&count| count > 0);
+
+    let servos: Vec<Servo> = (0..count).map(|_| Servo::new(common::ask_id())).collect();
+
+    println!("Recording for 10 seconds. Move the joints by hand.");
+    let recording = teach::record(
+        &servos,
+        Duration::from_millis(50),
+        Duration::from_secs(10),
+        &mut port,
+    )
+    .expect("Recording must work.");
+
+    let path = "recording.txt";
+    recording
+        .save(File::create(path).expect("Recording file must be created."))
+        .expect("Recording must be saved.");
+    println!("Saved {} frames to {path}.", recording.frames.len());
+
+    println!("Press enter to replay.");
+    let mut input = String::new();
+    stdin()
+        .read_line(&mut input)
+        .expect("stdin read_line must work.");
+
+    let file = File::open(path).expect("Recording file must open.");
+    let recording = Recording::load(BufReader::new(file)).expect("Recording must load.");
+    teach::replay(&recording, &servos, Duration::from_secs(1), &mut port)
+        .expect("Replay must work.");
+}
@@ -0,0 +1,43 @@
+/// Sweep a servo back and forth along S-curve trajectories.
+///
+extern crate waveshare_serial_servo;
+
+#[path = "./common/lib.rs"]
+mod common;
+
+use std::time::Duration;
+
+use angle::Deg;
+use waveshare_serial_servo::{
+    servo::{Position, Servo},
+    trajectory::{self, Constraints, Profile, SCurve},
+};
+
+fn main() {
+    let mut port = common::get_port();
+
+    println!("Servo ID?");
+    let id = common::ask_id();
+
+    let servo = Servo::new(id);
+
+    let constraints = Constraints {
+        velocity: 2000.0,
+        acceleration: 4000.0,
+        jerk: 20000.0,
+    };
+    let targets = [Position::new(Deg(45.0)), Position::new(Deg(315.0))];
+
+    for target in targets.into_iter().cycle() {
+        let start = servo
+            .read_position(&mut port)
+            .expect("Servo read position must work.");
+        let profile =
+            SCurve::new(start, target, constraints).expect("The constraints must be valid.");
+        println!("Moving to {target:?} over {:?}", profile.duration());
+
+        let result =
+            trajectory::stream(&[(&servo, &profile)], Duration::from_millis(20), &mut port);
+        println!("response: {result:?}");
+    }
+}
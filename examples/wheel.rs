@@ -9,7 +9,7 @@ use std::io::stdin;
 
 use waveshare_serial_servo::{
     hardware::address,
-    servo::{Acceleration, Assign, Mode, Servo, Speed},
+    servo::{Acceleration, Assign, Mode, Servo, WheelSpeed},
 };
 
 fn ask_speed() -> Option<f32> {
@@ -42,7 +42,7 @@ fn main() {
         let Some(speed) = ask_speed() else {
             continue;
         };
-        assign.set(Speed::new(speed));
+        assign.set(WheelSpeed::new(speed));
         println!("response: {:?}", servo.write(&assign, &mut port));
     }
 }
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use arbitrary::Arbitrary;
+use waveshare_serial_servo::{
+    hardware::ID,
+    servo::{
+        self, Acceleration, Assign, GoalTime, LimitPolicy, Mode, Position, Servo, SoftLimits,
+        Speed, Torque, TorqueLimit,
+    },
+    sim::Simulator,
+};
+
+#[derive(Debug, Arbitrary)]
+enum Property {
+    Position(u16),
+    Speed(u16),
+    Acceleration(u8),
+    GoalTime(u16),
+    TorqueLimit(u16),
+    Torque(u8),
+    Wheel(bool),
+    PositionGoal(u16, u16, u8),
+    TimedPositionGoal(u16, u16),
+}
+
+#[derive(Debug, Arbitrary)]
+struct SoftLimitsInput {
+    min: u16,
+    max: u16,
+    reject: bool,
+}
+
+#[derive(Debug, Arbitrary)]
+struct ServoInput {
+    id: u8,
+    limits: Option<SoftLimitsInput>,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Arbitrary)]
+pub struct Input {
+    servos: Vec<ServoInput>,
+    steps: u8,
+}
+
+fn apply(assign: Assign, property: &Property) -> Assign {
+    match *property {
+        Property::Position(value) => match Position::new_raw(value) {
+            Ok(value) => assign.with(value),
+            Err(_) => assign,
+        },
+        Property::Speed(value) => match Speed::new_raw(value) {
+            Ok(value) => assign.with(value),
+            Err(_) => assign,
+        },
+        Property::Acceleration(value) => match Acceleration::new_raw(value) {
+            Ok(value) => assign.with(value),
+            Err(_) => assign,
+        },
+        Property::GoalTime(value) => match GoalTime::new_raw(value) {
+            Ok(value) => assign.with(value),
+            Err(_) => assign,
+        },
+        Property::TorqueLimit(value) => match TorqueLimit::new_raw(value) {
+            Ok(value) => assign.with(value),
+            Err(_) => assign,
+        },
+        Property::Torque(value) => assign.with(match value % 3 {
+            0 => Torque::Disabled,
+            1 => Torque::Enabled,
+            _ => Torque::Calibrate,
+        }),
+        Property::Wheel(wheel) => assign.with(if wheel { Mode::Wheel } else { Mode::Position }),
+        Property::PositionGoal(position, speed, acceleration) => {
+            match (
+                Position::new_raw(position),
+                Speed::new_raw(speed),
+                Acceleration::new_raw(acceleration),
+            ) {
+                (Ok(position), Ok(speed), Ok(acceleration)) => {
+                    Assign::new_position_goal(position, speed, acceleration)
+                }
+                _ => assign,
+            }
+        }
+        Property::TimedPositionGoal(position, millis) => {
+            let time = GoalTime::new(Duration::from_millis(millis as u64));
+            match (Position::new_raw(position), time) {
+                (Ok(position), Ok(time)) => Assign::new_timed_position_goal(position, time),
+                _ => assign,
+            }
+        }
+    }
+}
+
+pub fn check(input: Input) {
+    let mut sim = Simulator::with_manual_clock();
+
+    let mut servos = Vec::new();
+    for input in &input.servos {
+        // Broadcast ids are kept, since writes to them must fail cleanly rather than panic.
+        let Ok(id) = ID::try_from(input.id) else {
+            continue;
+        };
+        if let ID::Single(_) = id {
+            sim.add_servo(id);
+        }
+
+        let mut servo = Servo::new(id);
+        if let Some(SoftLimitsInput { min, max, reject }) = input.limits {
+            let policy = if reject {
+                LimitPolicy::Reject
+            } else {
+                LimitPolicy::Clamp
+            };
+            if let (Ok(min), Ok(max)) = (Position::new_raw(min), Position::new_raw(max)) {
+                if let Ok(limits) = SoftLimits::new(min, max, policy) {
+                    servo = servo.with_limits(limits);
+                }
+            }
+        }
+
+        let assign = input.properties.iter().fold(Assign::new(), apply);
+        servos.push((servo, assign));
+    }
+
+    // Soft limits may reject a goal, but building and sending must never panic.
+    for (servo, assign) in &servos {
+        let _ = servo.write(assign, &mut sim);
+    }
+
+    let writes: Vec<(&Servo, &Assign)> = servos
+        .iter()
+        .map(|(servo, assign)| (servo, assign))
+        .collect();
+    let _ = servo::sync_write(&writes, &mut sim);
+
+    for _ in 0..input.steps {
+        sim.step(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,53 @@
+use arbitrary::Arbitrary;
+use waveshare_serial_servo::{
+    command::Command,
+    hardware::{
+        address::{ReadRegion, SyncWriteRegion, WriteRegion},
+        Instruction, ID,
+    },
+    serial,
+    sim::Simulator,
+};
+
+#[derive(Debug, Arbitrary)]
+pub struct Input {
+    start: u8,
+    length: u8,
+    data: Vec<u8>,
+    writes: Vec<(u8, Vec<u8>)>,
+}
+
+pub fn check(input: Input) {
+    let id = ID::single(1).unwrap();
+    let mut sim = Simulator::with_manual_clock();
+    for &(id, _) in &input.writes {
+        if let Ok(id @ ID::Single(_)) = ID::try_from(id) {
+            sim.add_servo(id);
+        }
+    }
+    sim.add_servo(id);
+
+    // Any region that is accepted must also encode and be understood by a servo.
+    if let Some(region) = ReadRegion::new(input.start, input.length) {
+        let length = region.length() as usize;
+        let response = serial::packet_tx_rx(Command::new(id, Instruction::read(region)), &mut sim)
+            .expect("A valid read must succeed.")
+            .expect("A single servo must respond.");
+        assert_eq!(response.payload.len(), length);
+    }
+
+    if let Some(region) = WriteRegion::new(input.start, input.data) {
+        serial::packet_tx_rx(Command::new(id, Instruction::write(region)), &mut sim)
+            .expect("A valid write must succeed.");
+    }
+
+    let writes = input
+        .writes
+        .into_iter()
+        .filter_map(|(id, data)| Some((ID::try_from(id).ok()?, data)))
+        .collect();
+    if let Some(region) = SyncWriteRegion::new(input.start, writes) {
+        let command = Command::new(ID::broadcast(), Instruction::sync_write(region));
+        serial::packet_tx_rx(command, &mut sim).expect("A valid sync write must succeed.");
+    }
+}
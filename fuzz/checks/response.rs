@@ -0,0 +1,8 @@
+use waveshare_serial_servo::response::Response;
+
+pub fn check(data: &[u8]) {
+    if let Ok(response) = Response::try_from(data) {
+        // Header, id, length, status and crc surround the payload.
+        assert_eq!(response.payload.len() + 6, data.len());
+    }
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../checks/regions.rs"]
+mod check;
+
+fuzz_target!(|input: check::Input| check::check(input));
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../checks/response.rs"]
+mod check;
+
+fuzz_target!(|data: &[u8]| check::check(data));
@@ -0,0 +1,509 @@
+/// Administer servos from the command line.
+///
+/// Run with `cargo run --features cli --bin wsservo -- --port <PORT> <COMMAND>`.
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    process::ExitCode,
+    thread::sleep,
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Map, Value};
+use serialport::SerialPort;
+use thiserror::Error;
+use waveshare_serial_servo::{
+    hardware::{
+        address::{self, Address, ReadRegion, Size},
+        IDError, ID,
+    },
+    servo::{
+        Acceleration, Assign, BaudRate, Mode, Position, PropertyError, Servo, ServoError, Speed,
+        Torque, WheelSpeed,
+    },
+    snapshot::{self, RestoreOptions, Snapshot, SnapshotError},
+    sniff,
+    telemetry::{self, Sample},
+    wait::{WaitConfig, WaitError},
+};
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("Serial port error: {0}")]
+    Port(#[from] serialport::Error),
+    #[error("{0}")]
+    Servo(#[from] ServoError),
+    #[error("{0}")]
+    Wait(#[from] WaitError),
+    #[error("{0}")]
+    Snapshot(#[from] SnapshotError),
+    #[error("File error: {0}")]
+    File(#[from] std::io::Error),
+    #[error("Invalid id: {0}")]
+    Id(#[from] IDError),
+    #[error("Invalid value: {0}")]
+    Property(#[from] PropertyError),
+    #[error("{0}")]
+    Usage(String),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "wsservo", about = "Administer waveshare serial servos.")]
+struct Cli {
+    /// Serial port the bus adapter is on, such as COM3 or /dev/ttyUSB0. Needed by every
+    /// command except diff.
+    #[arg(short, long)]
+    port: Option<String>,
+    /// Bus speed in bits per second.
+    #[arg(short, long, default_value_t = 1_000_000)]
+    baud: u32,
+    /// How long to wait for each response, in milliseconds.
+    #[arg(long, default_value_t = 20)]
+    timeout: u64,
+    /// Print results as JSON.
+    #[arg(long)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Ping every id, and list the servos that answer.
+    Scan,
+    /// Check that a servo answers.
+    Ping {
+        #[arg(short, long)]
+        id: u8,
+    },
+    /// Show a servo's present state and configuration.
+    Info {
+        #[arg(short, long)]
+        id: u8,
+    },
+    /// Read a register, by name or address.
+    Read {
+        #[arg(short, long)]
+        id: u8,
+        register: String,
+    },
+    /// Write a register, by name or address, with a value valid for it. EEPROM registers are
+    /// unlocked first.
+    Write {
+        #[arg(short, long)]
+        id: u8,
+        register: String,
+        value: u16,
+    },
+    /// Give a servo a new id.
+    SetId {
+        #[arg(short, long)]
+        id: u8,
+        new_id: u8,
+    },
+    /// Change the speed a servo listens at, in bits per second.
+    SetBaud {
+        #[arg(short, long)]
+        id: u8,
+        baud: u32,
+    },
+    /// Move to a position, in steps.
+    Move {
+        #[arg(short, long)]
+        id: u8,
+        position: u16,
+        /// Steps per second, or 0 for the maximum.
+        #[arg(short, long, default_value_t = 0)]
+        speed: u16,
+        /// In units of 100 steps per second squared, or 0 for the maximum.
+        #[arg(short, long, default_value_t = 0)]
+        acceleration: u8,
+        /// Block until the servo has stopped.
+        #[arg(short, long)]
+        wait: bool,
+    },
+    /// Switch to wheel mode and spin at a speed, in steps per second. Negative speeds turn
+    /// the other way.
+    Wheel {
+        #[arg(short, long)]
+        id: u8,
+        #[arg(allow_negative_numbers = true)]
+        speed: i16,
+    },
+    /// Enable or disable torque.
+    Torque {
+        #[arg(short, long)]
+        id: u8,
+        state: TorqueState,
+    },
+    /// Read every register.
+    Dump {
+        #[arg(short, long)]
+        id: u8,
+    },
+    /// Save every register to a file.
+    Backup {
+        #[arg(short, long)]
+        id: u8,
+        file: String,
+    },
+    /// Write the EEPROM settings saved by backup, then check them.
+    Restore {
+        #[arg(short, long)]
+        id: u8,
+        file: String,
+        /// Also restore the saved id.
+        #[arg(long)]
+        with_id: bool,
+        /// Also restore the saved baud rate. It is written last, and checked at the new rate.
+        #[arg(long)]
+        with_baud: bool,
+    },
+    /// Show the registers that differ between two backups.
+    Diff { left: String, right: String },
+    /// Print telemetry from one or more servos.
+    Monitor {
+        #[arg(short, long = "id", num_args = 1.., required = true)]
+        ids: Vec<u8>,
+        /// Time between samples, in milliseconds.
+        #[arg(long, default_value_t = 100)]
+        period: u64,
+        /// Stop after this many samples of each servo.
+        #[arg(short, long)]
+        count: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TorqueState {
+    On,
+    Off,
+}
+
+type Port = Box<dyn SerialPort>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            match cli.json {
+                true => eprintln!("{}", json!({ "error": e.to_string() })),
+                false => eprintln!("Error: {e}"),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), CliError> {
+    let print = |value: Value| output(cli.json, &value);
+
+    if let Command::Diff {
+        ref left,
+        ref right,
+    } = cli.command
+    {
+        let left = Snapshot::load(BufReader::new(File::open(left)?))?;
+        let right = Snapshot::load(BufReader::new(File::open(right)?))?;
+        let differences = left
+            .diff(&right)
+            .into_iter()
+            .map(|difference| {
+                json!({
+                    "address": difference.index,
+                    "name": difference.name,
+                    "left": difference.left,
+                    "right": difference.right,
+                })
+            })
+            .collect();
+        print(Value::Array(differences));
+        return Ok(());
+    }
+
+    let path = cli
+        .port
+        .as_ref()
+        .ok_or_else(|| CliError::Usage("A port must be given with --port.".into()))?;
+    let mut port = serialport::new(path, cli.baud)
+        .timeout(Duration::from_millis(cli.timeout))
+        .open()?;
+
+    match cli.command {
+        Command::Scan => print(scan(&mut port)),
+        Command::Ping { id } => {
+            let response = servo(id)?.ping(&mut port)?;
+            print(json!({ "id": id, "status": status(&response.errors) }));
+        }
+        Command::Info { id } => print(info(&servo(id)?, &mut port)?),
+        Command::Read { id, ref register } => {
+            let address = register_by_name(register)?;
+            print(read_register(&servo(id)?, address.as_ref(), &mut port)?);
+        }
+        Command::Write {
+            id,
+            ref register,
+            value,
+        } => {
+            let address = register_by_name(register)?;
+            write_register(&servo(id)?, address.as_ref(), value, &mut port)?;
+            print(read_register(&servo(id)?, address.as_ref(), &mut port)?);
+        }
+        Command::SetId { id, new_id } => {
+            let mut servo = servo(id)?;
+            servo.write_id(ID::single(new_id)?, &mut port)?;
+            servo.ping(&mut port)?;
+            print(json!({ "id": new_id }));
+        }
+        Command::SetBaud { id, baud } => {
+            let rate = BaudRate::from_bits_per_second(baud)
+                .ok_or_else(|| CliError::Usage(format!("Unsupported baud rate {baud}.")))?;
+            servo(id)?.write_baud_rate(rate, &mut port, switch_baud)?;
+            print(json!({ "id": id, "baud": baud }));
+        }
+        Command::Move {
+            id,
+            position,
+            speed,
+            acceleration,
+            wait,
+        } => {
+            let servo = servo(id)?;
+            let goal = Position::new_raw(position)?;
+            let assign = Assign::new_position_goal(
+                goal,
+                Speed::new_raw(speed)?,
+                Acceleration::new_raw(acceleration)?,
+            )
+            .with(Mode::Position);
+            servo.write(&assign, &mut port)?;
+
+            let position = match wait {
+                true => servo.wait_until_stopped(goal, &WaitConfig::default(), &mut port)?,
+                false => servo.read_position(&mut port)?,
+            };
+            print(json!({ "id": id, "goal": goal.value(), "position": position.value() }));
+        }
+        Command::Wheel { id, speed } => {
+            let assign = Assign::new()
+                .with(Mode::Wheel)
+                .with(WheelSpeed::new_raw(speed)?);
+            servo(id)?.write(&assign, &mut port)?;
+            print(json!({ "id": id, "speed": speed }));
+        }
+        Command::Torque { id, state } => {
+            let torque = match state {
+                TorqueState::On => Torque::Enabled,
+                TorqueState::Off => Torque::Disabled,
+            };
+            servo(id)?.write(&Assign::new().with(torque), &mut port)?;
+            print(json!({ "id": id, "torque": matches!(state, TorqueState::On) }));
+        }
+        Command::Dump { id } => {
+            let servo = servo(id)?;
+            let registers = address::all()
+                .map(|address| read_register(&servo, address.as_ref(), &mut port))
+                .collect::<Result<Vec<Value>, CliError>>()?;
+            print(Value::Array(registers));
+        }
+        Command::Backup { id, ref file } => {
+            let snapshot = Snapshot::read(&servo(id)?, &mut port)?;
+            snapshot.save(BufWriter::new(File::create(file)?))?;
+            print(json!({ "id": id, "file": file, "registers": snapshot.entries.len() }));
+        }
+        Command::Restore {
+            id,
+            ref file,
+            with_id,
+            with_baud,
+        } => {
+            let snapshot = Snapshot::load(BufReader::new(File::open(file)?))?;
+            let options = RestoreOptions { id: with_id };
+            let mut servo = servo(id)?;
+            snapshot::restore(&mut servo, &snapshot, options, &mut port)?;
+            if with_baud {
+                snapshot::restore_baud_rate(&servo, &snapshot, &mut port, switch_baud)?;
+            }
+            print(json!({ "id": servo.id().value(), "file": file }));
+        }
+        Command::Diff { .. } => unreachable!("Diff does not use the port."),
+        Command::Monitor {
+            ref ids,
+            period,
+            count,
+        } => {
+            let servos = ids
+                .iter()
+                .map(|&id| servo(id))
+                .collect::<Result<Vec<Servo>, CliError>>()?;
+            let period = Duration::from_millis(period);
+
+            for _ in 0..count.unwrap_or(usize::MAX) {
+                for servo in &servos {
+                    let sample = telemetry::read_sample(servo, &mut port)?;
+                    print(sample_json(&sample));
+                }
+                sleep(period);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn servo(id: u8) -> Result<Servo, CliError> {
+    Ok(Servo::new(ID::single(id)?))
+}
+
+/// Move the port to the rate a servo was just told to listen at.
+fn switch_baud(port: &mut Port, baud: BaudRate) -> std::io::Result<()> {
+    Ok(port.set_baud_rate(baud.bits_per_second())?)
+}
+
+fn register_by_name(name: &str) -> Result<Box<dyn Address>, CliError> {
+    let address = match name.parse::<u8>() {
+        Ok(index) => address::address_from(index).filter(|address| address.index() == index),
+        Err(_) => address::address_by_name(name),
+    };
+    address.ok_or_else(|| CliError::Usage(format!("Unknown register {name}.")))
+}
+
+fn scan(port: &mut Port) -> Value {
+    let found: Vec<Value> = ID::all_single()
+        .filter_map(|id| {
+            let response = Servo::new(id).ping(port).ok()?;
+            Some(json!({ "id": id.value(), "status": status(&response.errors) }))
+        })
+        .collect();
+    Value::Array(found)
+}
+
+fn info(servo: &Servo, port: &mut Port) -> Result<Value, CliError> {
+    let model = servo.read_word(address::Model, port)?;
+    let mode = servo.read_byte(address::Mode, port)?;
+    let torque = servo.read_byte(address::TorqueEnable, port)?;
+    let baud = servo.read_baud_rate(port)?;
+    let (min, max) = servo.read_angle_limits(port)?;
+    let offset = servo.read_offset(port)?;
+    let sample = telemetry::read_sample(servo, port)?;
+
+    let mode = match mode {
+        0 => "position",
+        1 => "wheel",
+        _ => "unknown",
+    };
+
+    let mut info = json!({
+        "model": format!("{model:#06x}"),
+        "baud": baud.bits_per_second(),
+        "mode": mode,
+        "torque": torque != 0,
+        "min_angle": min.0,
+        "max_angle": max.0,
+        "offset": offset.0,
+    });
+    if let (Value::Object(info), Value::Object(sample)) = (&mut info, sample_json(&sample)) {
+        info.extend(sample);
+    }
+    Ok(info)
+}
+
+fn read_register(servo: &Servo, address: &dyn Address, port: &mut Port) -> Result<Value, CliError> {
+    let length = match address.size() {
+        Size::Byte => 1,
+        Size::Word => 2,
+    };
+    let region = ReadRegion::new(address.index(), length).expect("Every register is readable.");
+    let response = servo.read(region, port)?;
+
+    let registers = sniff::registers(address.index(), &response.payload);
+    let value = registers.first().map_or(0, |register| register.value);
+    Ok(json!({ "address": address.index(), "name": address.name(), "value": value }))
+}
+
+fn write_register(
+    servo: &Servo,
+    address: &dyn Address,
+    value: u16,
+    port: &mut Port,
+) -> Result<(), CliError> {
+    let mut assign = Assign::new();
+    assign.set_register(address, value).map_err(|e| match e {
+        PropertyError::ReadOnly => CliError::Usage(format!("{} is read only.", address.name())),
+        e => CliError::Usage(format!("{value} is not valid for {}: {e}", address.name())),
+    })?;
+
+    if !address::is_eeprom(address.index()) {
+        return Ok(servo.write(&assign, port)?);
+    }
+
+    servo.write_eeprom_lock(false, port)?;
+    let result = servo.write(&assign, port);
+    servo.write_eeprom_lock(true, port)?;
+    Ok(result?)
+}
+
+fn status(errors: &Option<impl ToString>) -> Value {
+    match errors {
+        Some(errors) => Value::String(errors.to_string()),
+        None => Value::String("ok".into()),
+    }
+}
+
+fn sample_json(sample: &Sample) -> Value {
+    json!({
+        "id": sample.id.value(),
+        "position": sample.position.value(),
+        "speed": sample.speed,
+        "load": sample.load,
+        "voltage": sample.voltage as f32 / 10.0,
+        "temperature": sample.temperature,
+        "current": sample.current,
+        "moving": sample.moving,
+        "status": status(&sample.errors),
+    })
+}
+
+/// Print a value as JSON, or as lines of `key: value` text.
+fn output(json: bool, value: &Value) {
+    if json {
+        println!("{value}");
+        return;
+    }
+
+    match value {
+        Value::Array(values) if values.is_empty() => println!("None found."),
+        Value::Array(values) => values.iter().for_each(|value| println!("{}", line(value))),
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                println!("{key}: {}", text(value));
+            }
+        }
+        value => println!("{}", text(value)),
+    }
+}
+
+fn line(value: &Value) -> String {
+    match value {
+        Value::Object(fields) => fields_line(fields),
+        value => text(value),
+    }
+}
+
+fn fields_line(fields: &Map<String, Value>) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{key}={}", text(value)))
+        .collect();
+    fields.join(" ")
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::serial::Port;
+
+/// A port shared between threads, such as a background poller and the main program.
+///
+/// Lock the bus for each transaction, as in `servo.ping(&mut *bus.lock())`, rather than
+/// holding the lock, so the users take turns.
+#[derive(Debug)]
+pub struct Bus<P> {
+    port: Arc<Mutex<P>>,
+}
+
+impl<P> Clone for Bus<P> {
+    fn clone(&self) -> Self {
+        Self {
+            port: Arc::clone(&self.port),
+        }
+    }
+}
+
+impl<P: Port> Bus<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port: Arc::new(Mutex::new(port)),
+        }
+    }
+
+    /// Wait for exclusive use of the port.
+    pub fn lock(&self) -> MutexGuard<'_, P> {
+        // A panic elsewhere does not leave the port itself in a bad state.
+        self.port.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,175 @@
+use thiserror::Error;
+
+use crate::{
+    serial::Port,
+    servo::{Assign, Position, PropertyError, Servo, ServoError, Torque, STEPS_PER_TURN},
+};
+
+/// The position a calibrated joint reads at its center, half a turn from zero.
+const MIDPOINT: u16 = STEPS_PER_TURN / 2;
+
+#[derive(Debug, Error)]
+pub enum CalibrationError {
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+    #[error("After calibrating, the servo reads {position:?} instead of the midpoint.")]
+    Verify { position: Position },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CalibrationMethod {
+    /// Write 128 to `TorqueEnable`, letting the firmware rewrite its own offset.
+    Firmware,
+    /// Compute the offset on the host, and write it to `Ofs`.
+    Offset,
+}
+
+/// Zeroes a joint at its assembled center.
+///
+/// [`Calibration::begin`] disables torque so the joint can be placed by hand, then
+/// [`Calibration::finish`] makes that pose the midpoint, saves it to EEPROM, and checks the
+/// servo now reads the midpoint.
+#[derive(Debug)]
+pub struct Calibration<'a> {
+    servo: &'a Servo,
+    method: CalibrationMethod,
+    /// How far from the midpoint the servo may read afterwards, in steps.
+    tolerance: u16,
+}
+
+impl<'a> Calibration<'a> {
+    pub fn begin(
+        servo: &'a Servo,
+        method: CalibrationMethod,
+        tolerance: u16,
+        port: &mut impl Port,
+    ) -> Result<Self, ServoError> {
+        servo.write(&Assign::new().with(Torque::Disabled), port)?;
+
+        Ok(Self {
+            servo,
+            method,
+            tolerance,
+        })
+    }
+
+    /// Make the present position the midpoint, returning the verified position.
+    ///
+    /// Torque is left disabled.
+    pub fn finish(self, port: &mut impl Port) -> Result<Position, CalibrationError> {
+        match self.method {
+            CalibrationMethod::Firmware => {
+                let calibrate = Assign::new().with(Torque::Calibrate);
+                self.servo.write_eeprom(&calibrate, port)?;
+            }
+            CalibrationMethod::Offset => {
+                let present = self.servo.read_position(port)?.value() as i32;
+                let offset = self.servo.read_offset_steps(port)?;
+
+                // The offset is subtracted from the raw position, so fold the error into it,
+                // wrapped to the nearest equivalent offset.
+                let (turn, half) = (STEPS_PER_TURN as i32, STEPS_PER_TURN as i32 / 2);
+                let offset = (offset + present - MIDPOINT as i32 + half).rem_euclid(turn) - half;
+                if offset <= -half {
+                    return Err(ServoError::from(PropertyError::OutOfRange).into());
+                }
+                self.servo.write_offset_steps(offset, port)?;
+            }
+        }
+
+        let position = self.servo.read_position(port)?;
+        if position.value().abs_diff(MIDPOINT) > self.tolerance {
+            return Err(CalibrationError::Verify { position });
+        }
+
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::{address, ID},
+        sim::Simulator,
+    };
+
+    /// Calibrate a servo whose shaft sits at `shaft` steps, with `offset` already written.
+    fn calibrate(
+        method: CalibrationMethod,
+        shaft: f32,
+        offset: i32,
+        tolerance: u16,
+    ) -> (Simulator, Result<Position, CalibrationError>) {
+        let id = ID::single(1).unwrap();
+        let servo = Servo::new(id);
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id).set_position(shaft);
+        servo.write_offset_steps(offset, &mut sim).unwrap();
+
+        let calibration = Calibration::begin(&servo, method, tolerance, &mut sim).unwrap();
+        let result = calibration.finish(&mut sim);
+        (sim, result)
+    }
+
+    fn offset(sim: &Simulator) -> u16 {
+        sim.servo(ID::single(1).unwrap())
+            .unwrap()
+            .word(address::Ofs)
+    }
+
+    #[test]
+    fn firmware_calibration_centers_the_pose() {
+        let (sim, result) = calibrate(CalibrationMethod::Firmware, 3000.0, 0, 0);
+        assert_eq!(result.unwrap().value(), MIDPOINT);
+        assert_eq!(offset(&sim), 952);
+
+        let servo = sim.servo(ID::single(1).unwrap()).unwrap();
+        assert_eq!(servo.byte(address::TorqueEnable), 0);
+        assert_eq!(servo.byte(address::Lock), 1);
+    }
+
+    #[test]
+    fn offset_calibration_centers_the_pose() {
+        let (sim, result) = calibrate(CalibrationMethod::Offset, 1000.0, 0, 0);
+        assert_eq!(result.unwrap().value(), MIDPOINT);
+        // Negative offsets set the sign bit.
+        assert_eq!(offset(&sim), 0x800 | 1048);
+    }
+
+    #[test]
+    fn offset_calibration_folds_in_the_old_offset() {
+        let (sim, result) = calibrate(CalibrationMethod::Offset, 3000.0, 500, 0);
+        assert_eq!(result.unwrap().value(), MIDPOINT);
+        assert_eq!(offset(&sim), 952);
+    }
+
+    #[test]
+    fn offset_calibration_wraps_to_the_nearer_offset() {
+        // The shaft reads 2696, so the exact offset of 2148 steps wraps around to -1948.
+        let (sim, result) = calibrate(CalibrationMethod::Offset, 100.0, 1500, 0);
+        assert_eq!(result.unwrap().value(), MIDPOINT);
+        assert_eq!(offset(&sim), 0x800 | 1948);
+    }
+
+    #[test]
+    fn half_a_turn_away_cannot_be_centered() {
+        // The offset can only reach 2047 steps, one short of the pose.
+        let (_, result) = calibrate(CalibrationMethod::Firmware, 0.0, 0, 0);
+        assert!(matches!(
+            result,
+            Err(CalibrationError::Verify { position }) if position.value() == 2047
+        ));
+
+        let (_, result) = calibrate(CalibrationMethod::Firmware, 0.0, 0, 1);
+        assert_eq!(result.unwrap().value(), 2047);
+
+        let (_, result) = calibrate(CalibrationMethod::Offset, 0.0, 0, 1);
+        assert!(matches!(
+            result,
+            Err(CalibrationError::Servo(ServoError::Property(
+                PropertyError::OutOfRange
+            )))
+        ));
+    }
+}
@@ -0,0 +1,346 @@
+use std::{collections::BTreeMap, fs, io, path::Path, time::Duration};
+
+use angle::Deg;
+use serde::Deserialize;
+use serialport::SerialPort;
+use thiserror::Error;
+
+use crate::{
+    hardware::ID,
+    joint::Joint,
+    serial::Port,
+    servo::{Assign, LimitPolicy, Mode, PropertyError, Servo, ServoError},
+};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("IO Error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Config format is invalid: {0}")]
+    Format(#[from] toml::de::Error),
+    #[error("Joint {joint} is invalid: {reason}")]
+    Invalid { joint: String, reason: &'static str },
+    #[error("Joints {first} and {second} both use id {id}.")]
+    DuplicateId {
+        first: String,
+        second: String,
+        id: u8,
+    },
+    #[error("Serial port error: {0}")]
+    Port(#[from] serialport::Error),
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+    #[error("Joints did not answer on the bus: {0:?}")]
+    Missing(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BusConfig {
+    /// Such as COM3 or /dev/ttyUSB0.
+    pub port: String,
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+    /// How long to wait for each response, in milliseconds.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_baud() -> u32 {
+    1_000_000
+}
+
+fn default_timeout() -> u64 {
+    20
+}
+
+/// One servo driving one joint. Angles are in degrees.
+///
+/// `zero`, `inverted` and `ratio` set up the [`Joint`] built from this config, so they apply to
+/// goals sent through it as well as to the limits.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JointConfig {
+    pub id: u8,
+    #[serde(default = "default_mode")]
+    pub mode: Mode,
+    /// The joint's range of motion, only in position mode.
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// What to do with goals outside of `min` and `max`.
+    #[serde(default = "default_policy")]
+    pub policy: LimitPolicy,
+    /// The servo angle at the joint's zero.
+    #[serde(default = "default_zero")]
+    pub zero: f32,
+    /// Whether the joint turns the opposite way to the servo.
+    #[serde(default)]
+    pub inverted: bool,
+    /// Servo turns for each turn of the joint.
+    #[serde(default = "default_ratio")]
+    pub ratio: f32,
+}
+
+fn default_mode() -> Mode {
+    Mode::Position
+}
+
+fn default_policy() -> LimitPolicy {
+    LimitPolicy::Clamp
+}
+
+fn default_zero() -> f32 {
+    180.0
+}
+
+fn default_ratio() -> f32 {
+    1.0
+}
+
+impl JointConfig {
+    fn joint(&self, name: &str) -> Result<Joint, ConfigError> {
+        let invalid = |reason| ConfigError::Invalid {
+            joint: name.into(),
+            reason,
+        };
+
+        let id = ID::single(self.id).map_err(|_| invalid("id must be from 0 to 252"))?;
+        let joint = Joint::new(Servo::new(id))
+            .with_ratio(self.ratio)
+            .map_err(|_| invalid("ratio must be above zero"))?
+            .with_zero(Deg(self.zero))
+            .map_err(|_| invalid("zero must be within one turn"))?
+            .with_inverted(self.inverted)
+            .expect("A joint without limits can always be inverted.");
+
+        let (min, max) = match (self.min, self.max, self.mode) {
+            (None, None, _) => return Ok(joint),
+            (Some(min), Some(max), Mode::Position) => (min, max),
+            (_, _, Mode::Wheel) => return Err(invalid("limits only apply in position mode")),
+            _ => return Err(invalid("min and max must be given together")),
+        };
+
+        joint
+            .with_limits(Deg(min), Deg(max), self.policy)
+            .map_err(|e| match e {
+                PropertyError::MinNotBelowMax => invalid("min must be below max"),
+                _ => invalid("limits must be within the servo's range"),
+            })
+    }
+}
+
+/// A robot's bus and the joints on it, usually loaded from a TOML file like:
+///
+/// ```toml
+/// [bus]
+/// port = "/dev/ttyUSB0"
+///
+/// [joints.shoulder]
+/// id = 1
+/// min = -90.0
+/// max = 90.0
+/// ratio = 2.0
+///
+/// [joints.wheel]
+/// id = 2
+/// mode = "wheel"
+/// inverted = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RobotConfig {
+    pub bus: BusConfig,
+    pub joints: BTreeMap<String, JointConfig>,
+}
+
+impl RobotConfig {
+    /// Parse and validate a config.
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text)?;
+        config.joints()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Every joint, with soft limits set from its range.
+    pub fn joints(&self) -> Result<BTreeMap<String, Joint>, ConfigError> {
+        let mut ids: BTreeMap<u8, &str> = BTreeMap::new();
+        for (name, joint) in &self.joints {
+            if let Some(first) = ids.insert(joint.id, name) {
+                return Err(ConfigError::DuplicateId {
+                    first: first.into(),
+                    second: name.clone(),
+                    id: joint.id,
+                });
+            }
+        }
+
+        self.joints
+            .iter()
+            .map(|(name, joint)| Ok((name.clone(), joint.joint(name)?)))
+            .collect()
+    }
+
+    pub fn open_port(&self) -> Result<Box<dyn SerialPort>, ConfigError> {
+        Ok(serialport::new(&self.bus.port, self.bus.baud)
+            .timeout(Duration::from_millis(self.bus.timeout))
+            .open()?)
+    }
+
+    /// Open the configured port, then see [`RobotConfig::connect_with`].
+    pub fn connect(&self) -> Result<Robot<Box<dyn SerialPort>>, ConfigError> {
+        self.connect_with(self.open_port()?)
+    }
+
+    /// Check that every joint answers on `port`, then set each servo's mode.
+    pub fn connect_with<P: Port>(&self, mut port: P) -> Result<Robot<P>, ConfigError> {
+        let joints = self.joints()?;
+
+        let missing: Vec<String> = joints
+            .iter()
+            .filter(|(_, joint)| joint.servo().ping(&mut port).is_err())
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !missing.is_empty() {
+            return Err(ConfigError::Missing(missing));
+        }
+
+        for (name, joint) in &joints {
+            joint
+                .servo()
+                .write(&Assign::new().with(self.joints[name].mode), &mut port)?;
+        }
+
+        Ok(Robot { port, joints })
+    }
+}
+
+/// A connected robot, with its joints by name.
+#[derive(Debug)]
+pub struct Robot<P> {
+    pub port: P,
+    pub joints: BTreeMap<String, Joint>,
+}
+
+impl<P> Robot<P> {
+    pub fn joint(&self, name: &str) -> Option<&Joint> {
+        self.joints.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hardware::address, sim::Simulator};
+
+    const CONFIG: &str = r#"
+        [bus]
+        port = "/dev/ttyUSB0"
+
+        [joints.shoulder]
+        id = 1
+        min = -45.0
+        max = 45.0
+        ratio = 2.0
+
+        [joints.wheel]
+        id = 2
+        mode = "wheel"
+        inverted = true
+    "#;
+
+    fn invalid_reason(joint: &str) -> &'static str {
+        let text = format!("[bus]\nport = \"/dev/ttyUSB0\"\n[joints.arm]\n{joint}");
+        match RobotConfig::from_toml(&text) {
+            Err(ConfigError::Invalid { joint, reason }) => {
+                assert_eq!(joint, "arm");
+                reason
+            }
+            result => panic!("Expected an invalid joint, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn joints_convert_through_their_config() {
+        let config = RobotConfig::from_toml(CONFIG).unwrap();
+        assert_eq!(config.bus.baud, 1_000_000);
+        let joints = config.joints().unwrap();
+
+        let shoulder = &joints["shoulder"];
+        assert_eq!(shoulder.position(Deg(45.0)).unwrap().value(), 3072);
+        let limits = shoulder.servo().limits().unwrap();
+        assert_eq!((limits.min().value(), limits.max().value()), (1024, 3072));
+
+        let wheel = &joints["wheel"];
+        assert!(wheel.inverted());
+        assert_eq!(wheel.servo_angle(Deg(10.0)).0, 170.0);
+        assert!(wheel.servo().limits().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_joints() {
+        assert_eq!(invalid_reason("id = 254"), "id must be from 0 to 252");
+        assert_eq!(
+            invalid_reason("id = 1\nratio = 0.0"),
+            "ratio must be above zero"
+        );
+        assert_eq!(
+            invalid_reason("id = 1\nzero = 400.0"),
+            "zero must be within one turn"
+        );
+        assert_eq!(
+            invalid_reason("id = 1\nmin = 10.0"),
+            "min and max must be given together"
+        );
+        assert_eq!(
+            invalid_reason("id = 1\nmin = 10.0\nmax = -10.0"),
+            "min must be below max"
+        );
+        assert_eq!(
+            invalid_reason("id = 1\nmin = -200.0\nmax = 10.0"),
+            "limits must be within the servo's range"
+        );
+        assert_eq!(
+            invalid_reason("id = 1\nmode = \"wheel\"\nmin = -10.0\nmax = 10.0"),
+            "limits only apply in position mode"
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_ids_and_unknown_fields() {
+        let text = CONFIG.replace("id = 2", "id = 1");
+        assert!(matches!(
+            RobotConfig::from_toml(&text),
+            Err(ConfigError::DuplicateId { id: 1, .. })
+        ));
+
+        let text = CONFIG.replace("ratio = 2.0", "gear = 2.0");
+        assert!(matches!(
+            RobotConfig::from_toml(&text),
+            Err(ConfigError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn connect_checks_every_joint_answers() {
+        let config = RobotConfig::from_toml(CONFIG).unwrap();
+
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ID::single(1).unwrap());
+        match config.connect_with(sim) {
+            Err(ConfigError::Missing(missing)) => assert_eq!(missing, ["wheel"]),
+            result => panic!("Expected a missing joint, got {result:?}"),
+        }
+
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ID::single(1).unwrap());
+        sim.add_servo(ID::single(2).unwrap());
+        let robot = config.connect_with(sim).unwrap();
+        let wheel = robot.port.servo(ID::single(2).unwrap()).unwrap();
+        assert_eq!(wheel.byte(address::Mode), 1);
+        assert!(robot.joint("shoulder").is_some());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    serial::Port,
+    servo::{self, Acceleration, Assign, Position, Servo, ServoError, Speed},
+};
+
+/// Plan position goals for moves from `start` to `end`, so that they all finish together.
+///
+/// The longest move runs at `speed` and `acceleration`. Every other move has its acceleration
+/// scaled by its share of the longest distance, then its speed chosen so its trapezoidal
+/// profile takes the same time. A speed of zero means the maximum speed, and an acceleration of
+/// zero means none is applied.
+pub fn plan(
+    moves: &[(Position, Position)],
+    speed: Speed,
+    acceleration: Acceleration,
+) -> Vec<Assign> {
+    let distance = |(start, end): &(Position, Position)| start.value().abs_diff(end.value());
+    let longest = moves.iter().map(distance).max().unwrap_or(0);
+
+    let speed = match speed.value() {
+        0 => Speed::new_raw(Speed::MAX).expect("The maximum is a valid speed."),
+        _ => speed,
+    };
+    let duration = duration(longest as f32, speed.value() as f32, acceleration);
+
+    moves
+        .iter()
+        .map(|step| {
+            let distance = distance(step);
+            let acceleration = match (acceleration.value(), longest) {
+                (0, _) | (_, 0) => acceleration,
+                (value, longest) => {
+                    let ratio = distance as f32 / longest as f32;
+                    let value = ((value as f32 * ratio).round() as u8).clamp(1, value);
+                    Acceleration::new_raw(value).expect("A scaled acceleration is within range.")
+                }
+            };
+
+            let value = required_speed(distance as f32, duration, acceleration)
+                .round()
+                .clamp(1.0, speed.value() as f32);
+            let speed = Speed::new_raw(value as u16).expect("A scaled speed is within range.");
+
+            Assign::new_position_goal(step.1, speed, acceleration)
+        })
+        .collect()
+}
+
+/// Steps per second squared, or infinite when the servo applies no acceleration.
+fn steps_per_second_squared(acceleration: Acceleration) -> f32 {
+    match acceleration.value() {
+        0 => f32::INFINITY,
+        value => value as f32 * 100.0,
+    }
+}
+
+/// Time in seconds for a trapezoidal move over `distance` steps.
+fn duration(distance: f32, speed: f32, acceleration: Acceleration) -> f32 {
+    let acceleration = steps_per_second_squared(acceleration);
+    if distance >= speed.powi(2) / acceleration {
+        distance / speed + speed / acceleration
+    } else {
+        2.0 * (distance / acceleration).sqrt()
+    }
+}
+
+/// The peak speed that covers `distance` steps in `duration` seconds.
+fn required_speed(distance: f32, duration: f32, acceleration: Acceleration) -> f32 {
+    if duration <= 0.0 {
+        return f32::INFINITY;
+    }
+
+    let acceleration = steps_per_second_squared(acceleration);
+    if acceleration.is_infinite() {
+        return distance / duration;
+    }
+
+    // Solve distance / v + v / a = duration for the slower of the two speeds.
+    let discriminant = (duration * acceleration).powi(2) - 4.0 * distance * acceleration;
+    if discriminant < 0.0 {
+        // Even a triangular profile is too slow, so go as fast as the ramp allows.
+        duration * acceleration / 2.0
+    } else {
+        (duration * acceleration - discriminant.sqrt()) / 2.0
+    }
+}
+
+/// Read where each servo is, then move them all to their targets with one sync write, so
+/// they arrive at the same moment. See [`plan`].
+///
+/// Targets are checked against each servo's soft limits before planning, so a clamped target
+/// still arrives with the others.
+///
+/// Returns the positions the servos started from.
+pub fn coordinated_move(
+    targets: &[(&Servo, Position)],
+    speed: Speed,
+    acceleration: Acceleration,
+    port: &mut impl Port,
+) -> Result<Vec<Position>, ServoError> {
+    let ends = targets
+        .iter()
+        .map(|&(servo, end)| servo.limit(end))
+        .collect::<Result<Vec<Position>, _>>()?;
+
+    let starts = targets
+        .iter()
+        .map(|(servo, _)| servo.read_position(port))
+        .collect::<Result<Vec<Position>, ServoError>>()?;
+
+    let moves: Vec<(Position, Position)> = starts.iter().copied().zip(ends).collect();
+    let assigns = plan(&moves, speed, acceleration);
+
+    let writes: Vec<(&Servo, &Assign)> = targets
+        .iter()
+        .map(|&(servo, _)| servo)
+        .zip(&assigns)
+        .collect();
+    servo::sync_write(&writes, port)?;
+
+    Ok(starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        hardware::{address, ID},
+        sim::Simulator,
+    };
+
+    /// Move servos from the center by `distances`, returning the tick, in 10ms steps, at which
+    /// each one stopped moving.
+    fn arrival_ticks(distances: &[i32], speed: u16, acceleration: u8) -> Vec<usize> {
+        let mut sim = Simulator::with_manual_clock();
+        let servos: Vec<Servo> = (1..=distances.len() as u8)
+            .map(|id| {
+                let id = ID::single(id).unwrap();
+                sim.add_servo(id);
+                Servo::new(id)
+            })
+            .collect();
+        let targets: Vec<(&Servo, Position)> = servos
+            .iter()
+            .zip(distances)
+            .map(|(servo, &distance)| (servo, Position::new_raw((2048 + distance) as u16).unwrap()))
+            .collect();
+
+        let speed = Speed::new_raw(speed).unwrap();
+        let acceleration = Acceleration::new_raw(acceleration).unwrap();
+        let starts = coordinated_move(&targets, speed, acceleration, &mut sim).unwrap();
+        assert!(starts.iter().all(|start| start.value() == 2048));
+
+        let mut arrived = vec![None; servos.len()];
+        for tick in 1..=500 {
+            sim.step(Duration::from_millis(10));
+            for (servo, arrived) in servos.iter().zip(&mut arrived) {
+                let moving = sim.servo(servo.id()).unwrap().byte(address::Moving);
+                if moving == 0 && arrived.is_none() {
+                    *arrived = Some(tick);
+                }
+            }
+        }
+
+        for &(servo, target) in &targets {
+            let present = sim
+                .servo(servo.id())
+                .unwrap()
+                .word(address::PresentPosition);
+            assert_eq!(present, target.value());
+        }
+        arrived.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Every move must stop within one tick of the others.
+    fn assert_together(ticks: &[usize]) {
+        let first = ticks.iter().min().unwrap();
+        let last = ticks.iter().max().unwrap();
+        assert!(last - first <= 1, "Arrived at ticks {ticks:?}");
+    }
+
+    #[test]
+    fn moves_arrive_together() {
+        let ticks = arrival_ticks(&[1000, 500, -200], 1000, 20);
+        assert!(ticks[0] > 50, "The longest move took {} ticks", ticks[0]);
+        assert_together(&ticks);
+    }
+
+    #[test]
+    fn moves_without_acceleration_arrive_together() {
+        let ticks = arrival_ticks(&[1500, -600, 300], 800, 0);
+        assert_together(&ticks);
+    }
+
+    #[test]
+    fn the_longest_move_keeps_its_speed() {
+        let moves = [2048, 3048, 2548].map(|steps| Position::new_raw(steps).unwrap());
+        let plan = plan(
+            &[(moves[0], moves[1]), (moves[0], moves[2])],
+            Speed::new_raw(1000).unwrap(),
+            Acceleration::new_raw(20).unwrap(),
+        );
+
+        assert_eq!(plan[0].get_word(address::GoalSpeed), Some(1000));
+        assert_eq!(plan[0].get_byte(address::Acceleration), Some(20));
+        assert_eq!(plan[1].get_byte(address::Acceleration), Some(10));
+    }
+}
@@ -0,0 +1,568 @@
+use std::f32::consts::TAU;
+
+use thiserror::Error;
+
+use crate::{
+    joint::Joint,
+    odometry::Odometry,
+    serial::Port,
+    servo::{self, Acceleration, Assign, Mode, PropertyError, Servo, ServoError, WheelSpeed},
+};
+
+#[derive(Debug, Error)]
+pub enum DriveError {
+    #[error("The drive needs {expected} wheels, but {received} were given.")]
+    WheelCount { expected: usize, received: usize },
+    #[error("The {0} must be positive and finite.")]
+    Dimension(&'static str),
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+}
+
+/// A body velocity in the robot's frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Twist {
+    /// Forward, in meters per second.
+    pub x: f32,
+    /// To the left, in meters per second.
+    pub y: f32,
+    /// Counterclockwise seen from above, in radians per second.
+    pub rotation: f32,
+}
+
+/// Where a robot is, relative to where its odometry started.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pose {
+    /// In meters.
+    pub x: f32,
+    /// In meters.
+    pub y: f32,
+    /// Counterclockwise from the starting direction, in radians.
+    pub heading: f32,
+}
+
+impl Pose {
+    /// Move at `twist` for `dt` seconds, following the arc it describes.
+    pub fn integrate(&mut self, twist: Twist, dt: f32) {
+        let turn = twist.rotation * dt;
+        let (x, y) = (twist.x * dt, twist.y * dt);
+
+        // Straight moves are the limit of an arc as the turn goes to zero.
+        let (along, across) = if turn.abs() < 1e-6 {
+            (1.0, turn / 2.0)
+        } else {
+            (turn.sin() / turn, (1.0 - turn.cos()) / turn)
+        };
+        let dx = along * x - across * y;
+        let dy = across * x + along * y;
+
+        let (sin, cos) = self.heading.sin_cos();
+        self.x += cos * dx - sin * dy;
+        self.y += sin * dx + cos * dy;
+        self.heading = (self.heading + turn + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+    }
+}
+
+/// How a base's wheels combine into its motion. Wheel velocities are in radians per second,
+/// positive when the wheel drives its side of the robot forward.
+///
+/// Build one with [`Kinematics::mecanum`], [`Kinematics::differential`] or
+/// [`Kinematics::omni`], which check its dimensions. [`Drive`] and [`Odometry`] check them too.
+#[derive(Debug, Clone, Copy)]
+pub enum Kinematics {
+    /// Four mecanum wheels, in the order front left, front right, back left, back right, with
+    /// their rollers forming an X when seen from above.
+    Mecanum {
+        /// In meters.
+        wheel_radius: f32,
+        /// From the center to the front axle, in meters.
+        half_length: f32,
+        /// From the center to each wheel, in meters.
+        half_width: f32,
+    },
+    /// Two wheels, in the order left, right.
+    Differential {
+        /// In meters.
+        wheel_radius: f32,
+        /// Between the two wheels, in meters.
+        track_width: f32,
+    },
+    /// Three omni wheels, 120 degrees apart counterclockwise, starting with the front wheel.
+    /// Each is positive when it drives the robot counterclockwise.
+    Omni {
+        /// In meters.
+        wheel_radius: f32,
+        /// From the center to each wheel, in meters.
+        base_radius: f32,
+    },
+}
+
+impl Kinematics {
+    pub fn mecanum(
+        wheel_radius: f32,
+        half_length: f32,
+        half_width: f32,
+    ) -> Result<Self, DriveError> {
+        Self::Mecanum {
+            wheel_radius,
+            half_length,
+            half_width,
+        }
+        .validate()
+    }
+
+    pub fn differential(wheel_radius: f32, track_width: f32) -> Result<Self, DriveError> {
+        Self::Differential {
+            wheel_radius,
+            track_width,
+        }
+        .validate()
+    }
+
+    pub fn omni(wheel_radius: f32, base_radius: f32) -> Result<Self, DriveError> {
+        Self::Omni {
+            wheel_radius,
+            base_radius,
+        }
+        .validate()
+    }
+
+    /// Check every dimension is positive and finite, returning the kinematics unchanged.
+    pub fn validate(self) -> Result<Self, DriveError> {
+        let dimensions: &[(f32, &'static str)] = match self {
+            Self::Mecanum {
+                wheel_radius,
+                half_length,
+                half_width,
+            } => &[
+                (wheel_radius, "wheel radius"),
+                (half_length, "half length"),
+                (half_width, "half width"),
+            ],
+            Self::Differential {
+                wheel_radius,
+                track_width,
+            } => &[(wheel_radius, "wheel radius"), (track_width, "track width")],
+            Self::Omni {
+                wheel_radius,
+                base_radius,
+            } => &[(wheel_radius, "wheel radius"), (base_radius, "base radius")],
+        };
+
+        match dimensions
+            .iter()
+            .find(|(value, _)| !(value.is_finite() && *value > 0.0))
+        {
+            Some(&(_, name)) => Err(DriveError::Dimension(name)),
+            None => Ok(self),
+        }
+    }
+
+    pub fn wheel_count(&self) -> usize {
+        match self {
+            Self::Mecanum { .. } => 4,
+            Self::Differential { .. } => 2,
+            Self::Omni { .. } => 3,
+        }
+    }
+
+    /// In meters.
+    pub fn wheel_radius(&self) -> f32 {
+        match *self {
+            Self::Mecanum { wheel_radius, .. }
+            | Self::Differential { wheel_radius, .. }
+            | Self::Omni { wheel_radius, .. } => wheel_radius,
+        }
+    }
+
+    /// The wheel velocities that move the body at `twist`. Differential drives cannot move
+    /// sideways, so `twist.y` is ignored for them.
+    pub fn wheel_velocities(&self, twist: Twist) -> Vec<f32> {
+        let Twist { x, y, rotation } = twist;
+        match *self {
+            Self::Mecanum {
+                wheel_radius,
+                half_length,
+                half_width,
+            } => {
+                let turn = (half_length + half_width) * rotation;
+                [x - y - turn, x + y + turn, x + y - turn, x - y + turn]
+                    .map(|speed| speed / wheel_radius)
+                    .into()
+            }
+            Self::Differential {
+                wheel_radius,
+                track_width,
+            } => {
+                let turn = track_width / 2.0 * rotation;
+                [x - turn, x + turn]
+                    .map(|speed| speed / wheel_radius)
+                    .into()
+            }
+            Self::Omni {
+                wheel_radius,
+                base_radius,
+            } => omni_angles()
+                .map(|angle| {
+                    let (sin, cos) = angle.sin_cos();
+                    (-sin * x + cos * y + base_radius * rotation) / wheel_radius
+                })
+                .into(),
+        }
+    }
+
+    /// The body velocity produced by `wheels`, which holds one velocity for each wheel.
+    ///
+    /// When the wheels disagree, such as a slipping mecanum wheel, this is the closest fit.
+    pub fn twist(&self, wheels: &[f32]) -> Result<Twist, DriveError> {
+        if wheels.len() != self.wheel_count() {
+            return Err(DriveError::WheelCount {
+                expected: self.wheel_count(),
+                received: wheels.len(),
+            });
+        }
+
+        Ok(match *self {
+            Self::Mecanum {
+                wheel_radius,
+                half_length,
+                half_width,
+            } => {
+                let [fl, fr, bl, br] = [0, 1, 2, 3].map(|i| wheels[i] * wheel_radius / 4.0);
+                Twist {
+                    x: fl + fr + bl + br,
+                    y: -fl + fr + bl - br,
+                    rotation: (-fl + fr - bl + br) / (half_length + half_width),
+                }
+            }
+            Self::Differential {
+                wheel_radius,
+                track_width,
+            } => {
+                let [left, right] = [0, 1].map(|i| wheels[i] * wheel_radius);
+                Twist {
+                    x: (left + right) / 2.0,
+                    y: 0.0,
+                    rotation: (right - left) / track_width,
+                }
+            }
+            Self::Omni {
+                wheel_radius,
+                base_radius,
+            } => {
+                let mut twist = Twist::default();
+                for (angle, &wheel) in omni_angles().iter().zip(wheels) {
+                    let (sin, cos) = angle.sin_cos();
+                    let speed = wheel * wheel_radius;
+                    twist.x -= 2.0 / 3.0 * sin * speed;
+                    twist.y += 2.0 / 3.0 * cos * speed;
+                    twist.rotation += speed / (3.0 * base_radius);
+                }
+                twist
+            }
+        })
+    }
+}
+
+fn omni_angles() -> [f32; 3] {
+    [0.0, TAU / 3.0, 2.0 * TAU / 3.0]
+}
+
+/// Scale `wheels` down together so none is faster than `max`, keeping the direction the body
+/// moves in. Returns the scale applied, which is 1 when every wheel was already within `max`.
+pub fn normalize(wheels: &mut [f32], max: f32) -> f32 {
+    let fastest = wheels
+        .iter()
+        .fold(0.0f32, |fastest, wheel| fastest.max(wheel.abs()));
+    if fastest <= max {
+        return 1.0;
+    }
+
+    let scale = max / fastest;
+    wheels.iter_mut().for_each(|wheel| *wheel *= scale);
+    scale
+}
+
+/// A wheeled base, driving wheel mode servos through its [`Kinematics`].
+///
+/// Each wheel is a [`Joint`], so mirrored wheels are marked as inverted, and wheels behind a
+/// gear reduction have their ratio set.
+#[derive(Debug, Clone)]
+pub struct Drive {
+    kinematics: Kinematics,
+    wheels: Vec<Joint>,
+    /// The fastest any wheel may turn, in radians per second.
+    max_velocity: f32,
+    acceleration: Acceleration,
+}
+
+impl Drive {
+    /// `wheels` are in the order given by `kinematics`.
+    pub fn new(kinematics: Kinematics, wheels: Vec<Joint>) -> Result<Self, DriveError> {
+        let kinematics = kinematics.validate()?;
+        if wheels.len() != kinematics.wheel_count() {
+            return Err(DriveError::WheelCount {
+                expected: kinematics.wheel_count(),
+                received: wheels.len(),
+            });
+        }
+
+        // The slowest wheel limits them all.
+        let max_velocity = wheels
+            .iter()
+            .map(|wheel| wheel.velocity(WheelSpeed::MAX as i32).abs().to_radians())
+            .fold(f32::INFINITY, f32::min);
+
+        Ok(Self {
+            kinematics,
+            wheels,
+            max_velocity,
+            acceleration: Acceleration::new_raw(0).expect("Zero is a valid acceleration."),
+        })
+    }
+
+    /// Limit every wheel to `max_velocity` radians per second, or less when a wheel's servo is
+    /// slower than that.
+    pub fn with_max_velocity(mut self, max_velocity: f32) -> Self {
+        self.max_velocity = self.max_velocity.min(max_velocity.abs());
+        self
+    }
+
+    /// How quickly wheels change speed. Zero, the default, changes speed at once.
+    pub fn with_acceleration(mut self, acceleration: Acceleration) -> Self {
+        self.acceleration = acceleration;
+        self
+    }
+
+    pub fn kinematics(&self) -> Kinematics {
+        self.kinematics
+    }
+
+    pub fn wheels(&self) -> &[Joint] {
+        &self.wheels
+    }
+
+    /// Track how far this drive's wheels turn. See [`Odometry`].
+    pub fn odometry(&self) -> Odometry {
+        Odometry::new(self.kinematics, self.wheels.clone())
+            .expect("A drive has a wheel for each of its kinematics.")
+    }
+
+    /// Switch every wheel's servo to wheel mode.
+    pub fn enable(&self, port: &mut impl Port) -> Result<(), ServoError> {
+        let assign = Assign::new().with(Mode::Wheel);
+        let writes: Vec<(&Servo, &Assign)> = self
+            .wheels
+            .iter()
+            .map(|wheel| (wheel.servo(), &assign))
+            .collect();
+        servo::sync_write(&writes, port)
+    }
+
+    /// The goal for each wheel to move the body at `twist`, and the twist they produce.
+    ///
+    /// When a wheel would be faster than allowed, every wheel is slowed by the same factor, so
+    /// the body keeps its direction and curvature at a lower speed.
+    pub fn goals(&self, twist: Twist) -> Result<(Vec<Assign>, Twist), PropertyError> {
+        let mut velocities = self.kinematics.wheel_velocities(twist);
+        normalize(&mut velocities, self.max_velocity);
+
+        let goals = self
+            .wheels
+            .iter()
+            .zip(&velocities)
+            .map(|(wheel, velocity)| {
+                // The acceleration and speed are separate regions, which leaves any position
+                // goal alone. Each region goes to every wheel in one sync write.
+                Ok(Assign::new()
+                    .with(self.acceleration)
+                    .with(wheel.wheel_speed(velocity.to_degrees())?))
+            })
+            .collect::<Result<_, PropertyError>>()?;
+
+        let twist = self
+            .kinematics
+            .twist(&velocities)
+            .expect("Kinematics give a velocity for each of their wheels.");
+        Ok((goals, twist))
+    }
+
+    /// Move the body at `twist`, with one sync write so every wheel changes speed together.
+    /// Returns the twist actually commanded, after slowing any wheel that was too fast.
+    pub fn set_twist(&self, twist: Twist, port: &mut impl Port) -> Result<Twist, ServoError> {
+        let (goals, twist) = self.goals(twist)?;
+        let writes: Vec<(&Servo, &Assign)> =
+            self.wheels.iter().map(Joint::servo).zip(&goals).collect();
+        servo::sync_write(&writes, port)?;
+        Ok(twist)
+    }
+
+    pub fn stop(&self, port: &mut impl Port) -> Result<(), ServoError> {
+        self.set_twist(Twist::default(), port).map(|_| ())
+    }
+
+    /// Read each wheel's `PresentSpeed`, in radians per second.
+    pub fn read_wheel_velocities(&self, port: &mut impl Port) -> Result<Vec<f32>, ServoError> {
+        self.wheels
+            .iter()
+            .map(|wheel| Ok(wheel.read_velocity(port)?.to_radians()))
+            .collect()
+    }
+
+    /// Read how the body is moving, from the speed of its wheels. Integrate it with
+    /// [`Pose::integrate`] to track where the robot has gone.
+    pub fn read_twist(&self, port: &mut impl Port) -> Result<Twist, ServoError> {
+        let velocities = self.read_wheel_velocities(port)?;
+        Ok(self
+            .kinematics
+            .twist(&velocities)
+            .expect("A drive has a wheel for each of its kinematics."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use angle::Deg;
+
+    use super::*;
+    use crate::{
+        hardware::{address, ID},
+        servo::LimitPolicy,
+        sim::Simulator,
+    };
+
+    fn wheels(count: u8) -> Vec<Joint> {
+        (1..=count)
+            .map(|id| Joint::new(Servo::new(ID::single(id).unwrap())))
+            .collect()
+    }
+
+    #[test]
+    fn rejects_invalid_dimensions() {
+        assert!(matches!(
+            Kinematics::mecanum(0.0, 0.1, 0.1),
+            Err(DriveError::Dimension("wheel radius"))
+        ));
+        assert!(matches!(
+            Kinematics::mecanum(0.03, 0.1, -0.1),
+            Err(DriveError::Dimension("half width"))
+        ));
+        assert!(matches!(
+            Kinematics::differential(0.03, f32::NAN),
+            Err(DriveError::Dimension("track width"))
+        ));
+        assert!(matches!(
+            Kinematics::omni(0.03, f32::INFINITY),
+            Err(DriveError::Dimension("base radius"))
+        ));
+
+        let kinematics = Kinematics::Differential {
+            wheel_radius: 0.03,
+            track_width: 0.0,
+        };
+        assert!(matches!(
+            Drive::new(kinematics, wheels(2)),
+            Err(DriveError::Dimension("track width"))
+        ));
+        assert!(matches!(
+            Odometry::new(kinematics, wheels(2)),
+            Err(DriveError::Dimension("track width"))
+        ));
+    }
+
+    #[test]
+    fn twist_needs_a_velocity_for_each_wheel() {
+        let kinematics = Kinematics::omni(0.03, 0.1).unwrap();
+        assert!(matches!(
+            kinematics.twist(&[1.0, 2.0]),
+            Err(DriveError::WheelCount {
+                expected: 3,
+                received: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn twist_inverts_wheel_velocities() {
+        let twist = Twist {
+            x: 0.2,
+            y: -0.1,
+            rotation: 0.5,
+        };
+        let all = [
+            Kinematics::mecanum(0.03, 0.08, 0.1).unwrap(),
+            Kinematics::omni(0.03, 0.1).unwrap(),
+        ];
+        for kinematics in all {
+            let result = kinematics
+                .twist(&kinematics.wheel_velocities(twist))
+                .unwrap();
+            assert!((result.x - twist.x).abs() < 1e-5, "{kinematics:?}");
+            assert!((result.y - twist.y).abs() < 1e-5, "{kinematics:?}");
+            assert!(
+                (result.rotation - twist.rotation).abs() < 1e-5,
+                "{kinematics:?}"
+            );
+        }
+
+        let differential = Kinematics::differential(0.03, 0.2).unwrap();
+        let result = differential
+            .twist(&differential.wheel_velocities(twist))
+            .unwrap();
+        assert!((result.x - twist.x).abs() < 1e-5);
+        assert_eq!(result.y, 0.0);
+        assert!((result.rotation - twist.rotation).abs() < 1e-5);
+    }
+
+    #[test]
+    fn set_twist_drives_the_wheels() {
+        let ids = [1, 2].map(|id| ID::single(id).unwrap());
+        let mut sim = Simulator::with_manual_clock();
+        for id in ids {
+            sim.add_servo(id);
+        }
+
+        // Soft limits only apply to position goals, so they must not get in the way.
+        let wheels = Vec::from([
+            Joint::new(Servo::new(ids[0]))
+                .with_limits(Deg(-90.0), Deg(90.0), LimitPolicy::Reject)
+                .unwrap(),
+            Joint::new(Servo::new(ids[1])).with_inverted(true).unwrap(),
+        ]);
+        let drive = Drive::new(Kinematics::differential(0.03, 0.2).unwrap(), wheels).unwrap();
+
+        drive.enable(&mut sim).unwrap();
+        let twist = Twist {
+            x: 0.1,
+            y: 0.0,
+            rotation: 0.5,
+        };
+        let commanded = drive.set_twist(twist, &mut sim).unwrap();
+        assert!((commanded.x - twist.x).abs() < 1e-5);
+        assert!((commanded.rotation - twist.rotation).abs() < 1e-5);
+
+        sim.step(Duration::from_millis(100));
+        let measured = drive.read_twist(&mut sim).unwrap();
+        assert!((measured.x - twist.x).abs() < 1e-3, "{measured:?}");
+        assert!(
+            (measured.rotation - twist.rotation).abs() < 1e-2,
+            "{measured:?}"
+        );
+
+        for id in ids {
+            let servo = sim.servo(id).unwrap();
+            assert_eq!(servo.byte(address::Mode), 1);
+            assert_eq!(servo.word(address::GoalPosition), 2048);
+        }
+        // The mirrored right wheel turns its servo backwards.
+        let speeds = ids.map(|id| Servo::new(id).read_speed(&mut sim).unwrap());
+        assert!(speeds[0] > 0 && speeds[1] < 0, "{speeds:?}");
+
+        drive.stop(&mut sim).unwrap();
+        sim.step(Duration::from_millis(100));
+        let stopped = drive.read_twist(&mut sim).unwrap();
+        assert_eq!((stopped.x, stopped.rotation), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,445 @@
+use std::{
+    f32::consts::PI,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{
+    coordinated,
+    kinematics::{Chain, KinematicsError, Solver, Target},
+    serial::Port,
+    servo::{self, Acceleration, Assign, Position, Servo, ServoError, Speed},
+};
+
+#[derive(Debug, Error)]
+pub enum GaitError {
+    #[error("The gait's {0} is invalid.")]
+    Parameter(&'static str),
+    #[error("Leg {leg} cannot reach its foot position: {source}")]
+    Unreachable { leg: usize, source: KinematicsError },
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+}
+
+/// The order legs lift in. Legs are listed front to back, alternating left and right, so a
+/// hexapod's are left front, right front, left middle, right middle, left back, right back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Alternate legs lift together, half at a time. The fastest gait, trotting on four legs.
+    Tripod,
+    /// One leg lifts at a time, back to front on the left, then on the right. The slowest and
+    /// most stable gait.
+    Wave,
+    /// One leg on each side lifts at a time, back to front, with the sides half a cycle apart.
+    Ripple,
+}
+
+impl Pattern {
+    /// When each leg lifts, as a fraction of the cycle, and the fraction of the cycle each leg
+    /// spends on the ground.
+    fn schedule(self, legs: usize) -> (Vec<f32>, f32) {
+        let per_side = legs.div_ceil(2).max(1);
+        let offsets = (0..legs).map(|leg| {
+            let (side, from_front) = (leg % 2, leg / 2);
+            let from_back = (per_side - 1 - from_front) as f32;
+            match self {
+                Self::Tripod => ((side + from_front) % 2) as f32 / 2.0,
+                Self::Wave => (side * per_side) as f32 / legs as f32 + from_back / legs as f32,
+                Self::Ripple => (from_back / per_side as f32 + side as f32 / 2.0).fract(),
+            }
+        });
+
+        let stance = match self {
+            Self::Tripod => 0.5,
+            Self::Wave => 1.0 - 1.0 / legs as f32,
+            Self::Ripple => 1.0 - 1.0 / per_side as f32,
+        };
+        (offsets.collect(), stance)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GaitParameters {
+    /// How far each foot moves along the ground in one cycle, in meters.
+    pub stride: f32,
+    /// How high each foot lifts, in meters.
+    pub height: f32,
+    /// The direction of travel, counterclockwise from the body's x axis, in radians.
+    pub direction: f32,
+    /// How long one cycle takes.
+    pub period: Duration,
+    /// How often goals are sent.
+    pub tick: Duration,
+}
+
+impl GaitParameters {
+    /// Check the period and tick are above zero, and the distances and direction are finite.
+    fn validate(&self) -> Result<(), GaitError> {
+        let invalid = [
+            (self.period.is_zero(), "period"),
+            (self.tick.is_zero(), "tick"),
+            (!self.stride.is_finite(), "stride"),
+            (!self.height.is_finite(), "height"),
+            (!self.direction.is_finite(), "direction"),
+        ];
+        match invalid.into_iter().find(|&(invalid, _)| invalid) {
+            Some((_, name)) => Err(GaitError::Parameter(name)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for GaitParameters {
+    fn default() -> Self {
+        Self {
+            stride: 0.04,
+            height: 0.02,
+            direction: 0.0,
+            period: Duration::from_secs(1),
+            tick: Duration::from_millis(20),
+        }
+    }
+}
+
+/// One leg, as a chain from the body to the foot.
+///
+/// The chain's base is where the leg is mounted on the body, so foot positions are in the
+/// body's frame, with z up.
+#[derive(Debug, Clone)]
+pub struct Leg {
+    pub chain: Chain,
+    /// Where the foot rests at the middle of its stride.
+    pub home: [f32; 3],
+}
+
+/// Walks a legged robot by moving each foot along a step, solving each leg's joint angles, and
+/// sending every leg's goals with one sync write each tick.
+#[derive(Debug, Clone)]
+pub struct Gait {
+    legs: Vec<Leg>,
+    offsets: Vec<f32>,
+    /// The fraction of the cycle each foot is on the ground.
+    stance: f32,
+    parameters: GaitParameters,
+    solver: Solver,
+    /// Through the cycle, from 0 to 1.
+    phase: f32,
+    /// The last goals of each leg, used as the next solve's seed.
+    angles: Vec<Vec<f32>>,
+    positions: Vec<Vec<Position>>,
+}
+
+impl Gait {
+    pub fn new(
+        legs: Vec<Leg>,
+        pattern: Pattern,
+        parameters: GaitParameters,
+    ) -> Result<Self, GaitError> {
+        parameters.validate()?;
+        let (offsets, stance) = pattern.schedule(legs.len());
+        let angles = legs
+            .iter()
+            .map(|leg| {
+                leg.chain
+                    .ranges()
+                    .iter()
+                    .map(|&(min, max)| 0.0f32.clamp(min, max))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            legs,
+            offsets,
+            stance,
+            parameters,
+            solver: Solver::default(),
+            phase: 0.0,
+            angles,
+            positions: Vec::new(),
+        })
+    }
+
+    pub fn with_solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn parameters(&self) -> GaitParameters {
+        self.parameters
+    }
+
+    /// Change the gait from the next tick. Feet continue from where they are in the cycle.
+    pub fn set_parameters(&mut self, parameters: GaitParameters) -> Result<(), GaitError> {
+        parameters.validate()?;
+        self.parameters = parameters;
+        Ok(())
+    }
+
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Where `leg`'s foot is at `phase` through the cycle.
+    ///
+    /// On the ground, the foot moves back at a steady speed, pushing the body forward. In the
+    /// air, it lifts and returns to the front of its stride.
+    pub fn foot(&self, leg: usize, phase: f32) -> [f32; 3] {
+        let GaitParameters {
+            stride,
+            height,
+            direction,
+            ..
+        } = self.parameters;
+        let phase = (phase - self.offsets[leg]).rem_euclid(1.0);
+
+        let (along, lift) = if phase < self.stance {
+            (0.5 - phase / self.stance, 0.0)
+        } else {
+            let swing = (phase - self.stance) / (1.0 - self.stance);
+            ((1.0 - (PI * swing).cos()) / 2.0 - 0.5, (PI * swing).sin())
+        };
+
+        let [x, y, z] = self.legs[leg].home;
+        let (sin, cos) = direction.sin_cos();
+        [
+            x + cos * stride * along,
+            y + sin * stride * along,
+            z + height * lift,
+        ]
+    }
+
+    /// Solve every leg's goal positions at `phase`, seeding each from its last goals. The seeds
+    /// only change once every leg has solved.
+    fn solve(&mut self, phase: f32) -> Result<Vec<Vec<Position>>, GaitError> {
+        let mut angles = Vec::new();
+        let mut positions = Vec::new();
+        for leg in 0..self.legs.len() {
+            let target = Target::Position(self.foot(leg, phase));
+            let chain = &self.legs[leg].chain;
+            let solved = chain
+                .inverse(target, &self.angles[leg], &self.solver)
+                .map_err(|source| GaitError::Unreachable { leg, source })?;
+
+            positions.push(chain.positions(&solved).map_err(ServoError::from)?);
+            angles.push(solved);
+        }
+
+        self.angles = angles;
+        Ok(positions)
+    }
+
+    fn servos(&self) -> impl Iterator<Item = &Servo> {
+        self.legs
+            .iter()
+            .flat_map(|leg| leg.chain.links().iter().map(|(_, joint)| joint.servo()))
+    }
+
+    /// Move every foot to where the cycle starts, arriving together. See
+    /// [`coordinated::coordinated_move`].
+    pub fn start(
+        &mut self,
+        speed: Speed,
+        acceleration: Acceleration,
+        port: &mut impl Port,
+    ) -> Result<(), GaitError> {
+        self.phase = 0.0;
+        let positions = self.solve(0.0)?;
+        let targets: Vec<(&Servo, Position)> = self
+            .servos()
+            .zip(positions.iter().flatten().copied())
+            .collect();
+        coordinated::coordinated_move(&targets, speed, acceleration, port)?;
+
+        self.positions = positions;
+        Ok(())
+    }
+
+    /// Advance by one tick, and send every joint's goal with one sync write. Each joint's speed
+    /// is set so it arrives at the end of the tick.
+    ///
+    /// The phase only advances once the tick's goals are sent, so a failed tick can be retried.
+    /// Call [`Gait::start`] first, so the first tick does not jump.
+    pub fn tick(&mut self, port: &mut impl Port) -> Result<(), GaitError> {
+        let GaitParameters { period, tick, .. } = self.parameters;
+        let phase = (self.phase + tick.as_secs_f32() / period.as_secs_f32()).rem_euclid(1.0);
+
+        let positions = self.solve(phase)?;
+        let no_acceleration = Acceleration::new_raw(0).expect("Zero is a valid acceleration.");
+        let max_speed = Speed::MAX as f32;
+        let last: Vec<Position> = self.positions.iter().flatten().copied().collect();
+        let assigns: Vec<Assign> = positions
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, &goal)| {
+                // Without a last goal, move at the maximum speed.
+                let steps = last.get(index).map_or(0.0, |last| {
+                    let distance = last.value().abs_diff(goal.value()) as f32;
+                    (distance / tick.as_secs_f32()).ceil().clamp(1.0, max_speed)
+                });
+                let speed = Speed::new_raw(steps as u16).expect("The speed is within range.");
+                Assign::new_position_goal(goal, speed, no_acceleration)
+            })
+            .collect();
+
+        let writes: Vec<(&Servo, &Assign)> = self.servos().zip(&assigns).collect();
+        servo::sync_write(&writes, port)?;
+
+        self.phase = phase;
+        self.positions = positions;
+        Ok(())
+    }
+
+    /// Tick at a fixed rate for `duration`. A tick that runs late is not made up.
+    pub fn run(&mut self, duration: Duration, port: &mut impl Port) -> Result<(), GaitError> {
+        let start = Instant::now();
+        let mut deadline = start;
+
+        while deadline - start < duration {
+            self.tick(port)?;
+
+            let now = Instant::now();
+            deadline += self.parameters.tick;
+            if now > deadline {
+                deadline = now;
+            }
+            sleep(deadline - now);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use angle::Deg;
+
+    use super::*;
+    use crate::{
+        hardware::{address, ID},
+        joint::Joint,
+        kinematics::Link,
+        servo::LimitPolicy,
+        sim::Simulator,
+    };
+
+    /// A flat leg of two 0.1m links, turning about z, with its foot resting at `home`.
+    fn leg(ids: [u8; 2], home: [f32; 3]) -> Leg {
+        let links = ids
+            .map(|id| {
+                let joint = Joint::new(Servo::new(ID::single(id).unwrap()))
+                    .with_limits(Deg(-170.0), Deg(170.0), LimitPolicy::Reject)
+                    .unwrap();
+                let link = Link {
+                    a: 0.1,
+                    ..Link::default()
+                };
+                (link, joint)
+            })
+            .into();
+        Leg {
+            chain: Chain::new(links),
+            home,
+        }
+    }
+
+    /// Feet that never lift, so a flat leg can follow them.
+    fn flat() -> GaitParameters {
+        GaitParameters {
+            height: 0.0,
+            ..GaitParameters::default()
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let legs = || Vec::from([leg([1, 2], [0.1, 0.1, 0.0])]);
+        let with = |change: fn(&mut GaitParameters)| {
+            let mut parameters = flat();
+            change(&mut parameters);
+            parameters
+        };
+
+        let zero_period = with(|parameters| parameters.period = Duration::ZERO);
+        assert!(matches!(
+            Gait::new(legs(), Pattern::Tripod, zero_period),
+            Err(GaitError::Parameter("period"))
+        ));
+
+        let mut gait = Gait::new(legs(), Pattern::Tripod, flat()).unwrap();
+        let zero_tick = with(|parameters| parameters.tick = Duration::ZERO);
+        assert!(matches!(
+            gait.set_parameters(zero_tick),
+            Err(GaitError::Parameter("tick"))
+        ));
+        let nan_stride = with(|parameters| parameters.stride = f32::NAN);
+        assert!(matches!(
+            gait.set_parameters(nan_stride),
+            Err(GaitError::Parameter("stride"))
+        ));
+        assert_eq!(gait.parameters().tick, flat().tick);
+    }
+
+    #[test]
+    fn ticks_send_reachable_goals() {
+        let legs = Vec::from([leg([1, 2], [0.1, 0.1, 0.0]), leg([3, 4], [0.1, -0.1, 0.0])]);
+        let mut gait = Gait::new(legs, Pattern::Tripod, flat()).unwrap();
+        let mut sim = Simulator::with_manual_clock();
+        for id in 1..=4 {
+            sim.add_servo(ID::single(id).unwrap());
+        }
+
+        gait.start(Speed::new(1.0), Acceleration::new(0.0), &mut sim)
+            .unwrap();
+        sim.step(Duration::from_secs(1));
+        for _ in 0..10 {
+            gait.tick(&mut sim).unwrap();
+            sim.step(gait.parameters().tick);
+
+            for (index, leg) in gait.legs.iter().enumerate() {
+                let (goals, present): (Vec<Position>, Vec<u16>) = leg
+                    .chain
+                    .links()
+                    .iter()
+                    .map(|(_, joint)| {
+                        let servo = sim.servo(joint.servo().id()).unwrap();
+                        let goal = servo.word(address::GoalPosition);
+                        let present = servo.word(address::PresentPosition);
+                        (Position::new_raw(goal).unwrap(), present)
+                    })
+                    .unzip();
+
+                // Each joint arrives by the end of the tick.
+                let values: Vec<u16> = goals.iter().map(|goal| goal.value()).collect();
+                assert_eq!(present, values, "Leg {index}");
+
+                // And the goals put the foot where the gait wants it.
+                let foot = leg.chain.forward(&leg.chain.angles(&goals)).position();
+                let expected = gait.foot(index, gait.phase());
+                for (actual, expected) in foot.iter().zip(expected) {
+                    assert!(
+                        (actual - expected).abs() < 1e-3,
+                        "Leg {index} at {foot:?}, expected {expected:?}"
+                    );
+                }
+            }
+        }
+        assert!((gait.phase() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn a_failed_solve_keeps_every_seed() {
+        // The second leg's foot is out of reach.
+        let legs = Vec::from([leg([1, 2], [0.1, 0.1, 0.0]), leg([3, 4], [0.5, 0.0, 0.0])]);
+        let mut gait = Gait::new(legs, Pattern::Tripod, flat()).unwrap();
+        let seeds = gait.angles.clone();
+        let mut sim = Simulator::with_manual_clock();
+
+        let result = gait.tick(&mut sim);
+        assert!(matches!(result, Err(GaitError::Unreachable { leg: 1, .. })));
+        assert_eq!(gait.angles, seeds);
+        assert_eq!(gait.phase(), 0.0);
+    }
+}
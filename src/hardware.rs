@@ -12,7 +12,12 @@ pub enum IDError {
     MustBroadcast,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
 pub enum ID {
     Broadcast,
     Single(u8),
@@ -42,7 +47,7 @@ impl ID {
     pub fn value(self) -> u8 {
         match self {
             Self::Broadcast => Self::BROADCAST,
-            Self::Single(x) => x.into(),
+            Self::Single(x) => x,
         }
     }
 }
@@ -65,6 +70,50 @@ impl TryFrom<u8> for ID {
     }
 }
 
+/// The sign bit of the `Ofs` register.
+pub(crate) const OFFSET_SIGN_BIT: u8 = 11;
+
+/// The sign bit of the `GoalSpeed` and `PresentSpeed` registers.
+pub(crate) const SPEED_SIGN_BIT: u8 = 15;
+
+/// The sign bit of the `PresentLoad` register.
+pub(crate) const LOAD_SIGN_BIT: u8 = 10;
+
+/// The sign bit of a register stored as sign and magnitude, given its index.
+pub(crate) fn sign_bit(index: u8) -> Option<u8> {
+    use address::Address;
+
+    [
+        (address::Ofs.index(), OFFSET_SIGN_BIT),
+        (address::GoalSpeed.index(), SPEED_SIGN_BIT),
+        (address::PresentSpeed.index(), SPEED_SIGN_BIT),
+        (address::PresentLoad.index(), LOAD_SIGN_BIT),
+    ]
+    .into_iter()
+    .find(|&(register, _)| register == index)
+    .map(|(_, sign_bit)| sign_bit)
+}
+
+/// Encode a value in the servo's sign-magnitude format, where `sign_bit` marks a negative value.
+pub(crate) fn encode_signed(value: i32, sign_bit: u8) -> u16 {
+    let magnitude = value.unsigned_abs().min((1 << sign_bit) - 1) as u16;
+    if value < 0 {
+        magnitude | 1 << sign_bit
+    } else {
+        magnitude
+    }
+}
+
+/// Decode a value in the servo's sign-magnitude format, where `sign_bit` marks a negative value.
+pub(crate) fn decode_signed(value: u16, sign_bit: u8) -> i32 {
+    let magnitude = (value & ((1 << sign_bit) - 1)) as i32;
+    if value & 1 << sign_bit != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
 pub mod address {
     macro_rules! address {
         ($name:ident, $value:expr, Byte, Read) => {
@@ -75,6 +124,10 @@ pub mod address {
                     $value
                 }
 
+                fn name(&self) -> &'static str {
+                    stringify!($name)
+                }
+
                 fn size(&self) -> Size {
                     Size::Byte
                 }
@@ -95,6 +148,10 @@ pub mod address {
                     $value
                 }
 
+                fn name(&self) -> &'static str {
+                    stringify!($name)
+                }
+
                 fn size(&self) -> Size {
                     Size::Byte
                 }
@@ -116,6 +173,10 @@ pub mod address {
                     $value
                 }
 
+                fn name(&self) -> &'static str {
+                    stringify!($name)
+                }
+
                 fn size(&self) -> Size {
                     Size::Word
                 }
@@ -136,6 +197,10 @@ pub mod address {
                     $value
                 }
 
+                fn name(&self) -> &'static str {
+                    stringify!($name)
+                }
+
                 fn size(&self) -> Size {
                     Size::Word
                 }
@@ -179,6 +244,12 @@ pub mod address {
     pub trait Address {
         fn index(&self) -> u8;
 
+        /// The register's name. Addresses defined outside this crate are `"Unknown"` unless
+        /// they say otherwise.
+        fn name(&self) -> &'static str {
+            "Unknown"
+        }
+
         fn size(&self) -> Size;
 
         fn access(&self) -> Access;
@@ -216,6 +287,7 @@ pub mod address {
     address!(GoalPosition, 42, Word, ReadWrite);
     address!(GoalTime, 44, Word, ReadWrite);
     address!(GoalSpeed, 46, Word, ReadWrite);
+    address!(TorqueLimit, 48, Word, ReadWrite);
     address!(Lock, 55, Byte, ReadWrite);
 
     // SRAM. Read only.
@@ -243,6 +315,7 @@ pub mod address {
             42 | 43 => Box::new(GoalPosition),
             44 | 45 => Box::new(GoalTime),
             46 | 47 => Box::new(GoalSpeed),
+            48 | 49 => Box::new(TorqueLimit),
             55 => Box::new(Lock),
             56 | 57 => Box::new(PresentPosition),
             58 | 59 => Box::new(PresentSpeed),
@@ -255,6 +328,21 @@ pub mod address {
         })
     }
 
+    /// Whether a register is stored in EEPROM, which must be unlocked before writing.
+    pub fn is_eeprom(index: u8) -> bool {
+        index < TorqueEnable.index()
+    }
+
+    /// Every register, once each, in address order.
+    pub fn all() -> impl Iterator<Item = Box<dyn Address>> {
+        (0..=u8::MAX).filter_map(|index| address_from(index).filter(|a| a.index() == index))
+    }
+
+    /// Find a register by its name, ignoring case.
+    pub fn address_by_name(name: &str) -> Option<Box<dyn Address>> {
+        all().find(|address| address.name().eq_ignore_ascii_case(name))
+    }
+
     pub fn writeable_address_from(value: u8) -> Option<Box<dyn WriteableAddress>> {
         Some(match value {
             5 => Box::new(ID),
@@ -270,6 +358,7 @@ pub mod address {
             42 | 43 => Box::new(GoalPosition),
             44 | 45 => Box::new(GoalTime),
             46 | 47 => Box::new(GoalSpeed),
+            48 | 49 => Box::new(TorqueLimit),
             55 => Box::new(Lock),
             _ => return None,
         })
@@ -283,10 +372,10 @@ pub mod address {
 
     impl WriteRegion {
         pub fn new(start: u8, data: Vec<u8>) -> Option<Self> {
-            let end = start + data.len() as u8;
-            (start..end)
-                .all(|i| address_from(i).is_some_and(|a| a.access().can_write()))
-                .then(|| Self { start, data })
+            let end = start.checked_add(data.len().try_into().ok()?)?;
+            let valid =
+                (start..end).all(|i| address_from(i).is_some_and(|a| a.access().can_write()));
+            (valid && !data.is_empty()).then_some(Self { start, data })
         }
 
         pub fn one<A: WriteableAddress>(address: A, value: u8) -> Self {
@@ -297,6 +386,36 @@ pub mod address {
         }
     }
 
+    /// The same region written to several servos with a single packet.
+    #[derive(Debug, Clone)]
+    pub struct SyncWriteRegion {
+        pub(crate) start: u8,
+        pub(crate) length: u8,
+        pub(crate) writes: Vec<(super::ID, Vec<u8>)>,
+    }
+
+    impl SyncWriteRegion {
+        /// Every write must be to a single servo, and hold the same number of bytes.
+        pub fn new(start: u8, writes: Vec<(super::ID, Vec<u8>)>) -> Option<Self> {
+            let length = writes.first()?.1.len();
+            let valid = writes.iter().all(|(id, data)| {
+                matches!(id, super::ID::Single(_))
+                    && data.len() == length
+                    && WriteRegion::new(start, data.clone()).is_some()
+            });
+
+            // The instruction, start, length, each write and the crc must fit the length field.
+            let packet_length = writes.len().checked_mul(length + 1)?.checked_add(4)?;
+            let valid = valid && packet_length <= u8::MAX as usize;
+
+            valid.then_some(Self {
+                start,
+                length: length.try_into().ok()?,
+                writes,
+            })
+        }
+    }
+
     #[derive(Debug, Clone)]
     pub struct ReadRegion {
         pub(crate) start: u8,
@@ -305,58 +424,103 @@ pub mod address {
 
     impl ReadRegion {
         pub fn new(start: u8, length: u8) -> Option<Self> {
-            let end = start + length;
-            (start..end)
-                .all(|i| address_from(i).is_some_and(|a| a.access().can_read()))
-                .then(|| Self { start, length })
+            let end = start.checked_add(length)?;
+            let valid =
+                (start..end).all(|i| address_from(i).is_some_and(|a| a.access().can_read()));
+            (valid && length > 0).then_some(Self { start, length })
         }
 
         pub fn one<W: ReadableAddress>(address: W) -> Self {
+            let length = match address.size() {
+                Size::Byte => 1,
+                Size::Word => 2,
+            };
+
             Self {
                 start: address.index(),
-                length: 1,
+                length,
             }
         }
+
+        pub fn length(&self) -> u8 {
+            self.length
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Error)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "u8", into = "u8")
+)]
 pub struct DriverErrors {
     errors: u8,
 }
 
 impl Display for DriverErrors {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let errors: Vec<String> = DriverError::VALUES
-            .into_iter()
-            .filter(|&error| self.contains(error))
-            .map(|error| format!("{error}"))
-            .collect();
+        let mut errors: Vec<String> = self.iter().map(|error| format!("{error}")).collect();
+        if self.unknown() != 0 {
+            errors.push(format!("Unknown status bits {:#010b}.", self.unknown()));
+        }
         write!(f, "DriverErrors: {errors:?}")
     }
 }
 
+impl From<u8> for DriverErrors {
+    fn from(raw: u8) -> Self {
+        Self::new(raw)
+    }
+}
+
+impl From<DriverErrors> for u8 {
+    fn from(errors: DriverErrors) -> Self {
+        errors.raw()
+    }
+}
+
 impl DriverErrors {
-    fn contains(self, error: DriverError) -> bool {
+    /// Every bit of the status byte, including bits with no known meaning.
+    pub fn new(raw: u8) -> Self {
+        Self { errors: raw }
+    }
+
+    pub fn raw(self) -> u8 {
+        self.errors
+    }
+
+    pub fn contains(self, error: DriverError) -> bool {
         self.errors & error as u8 > 0
     }
 
-    pub(crate) fn from_byte(value: u8) -> Option<Self> {
-        let errors = Self { errors: value };
+    /// The known errors that are set.
+    pub fn iter(self) -> impl Iterator<Item = DriverError> {
+        DriverError::VALUES
+            .into_iter()
+            .filter(move |&error| self.contains(error))
+    }
 
-        if !DriverError::VALUES
+    /// The set bits that do not match a known [`DriverError`].
+    pub fn unknown(self) -> u8 {
+        let known = DriverError::VALUES
             .into_iter()
-            .any(|error| errors.contains(error))
-        {
-            return None;
-        }
+            .fold(0, |known, error| known | error as u8);
+        self.errors & !known
+    }
+
+    pub fn has_known(self) -> bool {
+        self.iter().next().is_some()
+    }
 
-        Some(errors)
+    /// Any status with a bit set, known or not.
+    pub(crate) fn from_byte(value: u8) -> Option<Self> {
+        (value != 0).then_some(Self { errors: value })
     }
 }
 
 #[repr(u8)]
-#[derive(Debug, Error, Clone, Copy)]
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
 pub enum DriverError {
     #[error("Input voltage out of allowed range.")]
     Voltage = 1,
@@ -385,6 +549,8 @@ pub enum Instruction {
     Ping,
     Read(address::ReadRegion),
     Write(address::WriteRegion),
+    /// Must be sent to [`ID::Broadcast`].
+    SyncWrite(address::SyncWriteRegion),
 }
 
 impl Instruction {
@@ -400,13 +566,33 @@ impl Instruction {
         Self::Write(region)
     }
 
+    pub fn sync_write(region: address::SyncWriteRegion) -> Self {
+        Self::SyncWrite(region)
+    }
+
+    /// The payload length of the response this instruction should produce.
+    pub(crate) fn response_length(&self) -> usize {
+        match self {
+            Self::Read(region) => region.length as usize,
+            Self::Ping | Self::Write(_) | Self::SyncWrite(_) => 0,
+        }
+    }
+
     pub(crate) fn data(self) -> Vec<u8> {
         match self {
             Self::Ping => Vec::from([0x01]),
-            Self::Read(region) => Vec::from([0x02, region.start as u8, region.length]),
+            Self::Read(region) => Vec::from([0x02, region.start, region.length]),
             Self::Write(region) => {
-                Vec::from_iter([0x03, region.start as u8].into_iter().chain(region.data))
+                Vec::from_iter([0x03, region.start].into_iter().chain(region.data))
             }
+            Self::SyncWrite(region) => Vec::from_iter(
+                [0x83, region.start, region.length].into_iter().chain(
+                    region
+                        .writes
+                        .into_iter()
+                        .flat_map(|(id, data)| std::iter::once(id.value()).chain(data)),
+                ),
+            ),
         }
     }
 }
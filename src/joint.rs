@@ -0,0 +1,402 @@
+use angle::{Angle, Deg};
+
+use crate::{
+    serial::Port,
+    servo::{
+        Acceleration, Assign, LimitPolicy, Mode, Position, PropertyError, Servo, ServoError,
+        SoftLimits, Speed, WheelSpeed, STEPS_PER_TURN,
+    },
+    telemetry::{self, Sample},
+};
+
+/// A joint's state, converted from a servo [`Sample`].
+#[derive(Debug, Clone, Copy)]
+pub struct JointState {
+    pub angle: Deg<f32>,
+    /// In degrees per second.
+    pub velocity: f32,
+    /// Signed, in 0.1% of the servo's maximum, positive when pushing towards larger angles.
+    pub load: i32,
+}
+
+/// A servo driving a joint through a gear or belt reduction, possibly mounted mirrored.
+///
+/// Angles, velocities and accelerations are measured at the joint, in degrees. The servo angle
+/// is `zero + ratio * angle`, or `zero - ratio * angle` when inverted.
+#[derive(Debug, Clone)]
+pub struct Joint {
+    servo: Servo,
+    /// Servo turns for each turn of the joint.
+    ratio: f32,
+    /// Whether the joint turns the opposite way to the servo.
+    inverted: bool,
+    /// The servo angle at the joint's zero.
+    zero: Deg<f32>,
+    /// Limits in joint angles, converted again whenever the conversion changes.
+    limits: Option<(Deg<f32>, Deg<f32>, LimitPolicy)>,
+}
+
+impl Joint {
+    /// A joint turning one to one with `servo`, with its zero at the servo's center.
+    pub fn new(servo: Servo) -> Self {
+        Self {
+            servo,
+            ratio: 1.0,
+            inverted: false,
+            zero: Deg(180.0),
+            limits: None,
+        }
+    }
+
+    /// Fails when the ratio is not above zero, or would take any limits out of the servo's
+    /// range.
+    pub fn with_ratio(mut self, ratio: f32) -> Result<Self, PropertyError> {
+        if !(ratio.is_finite() && ratio > 0.0) {
+            return Err(PropertyError::OutOfRange);
+        }
+        self.ratio = ratio;
+        self.convert_limits()
+    }
+
+    /// Fails when inverting would take any limits out of the servo's range.
+    pub fn with_inverted(mut self, inverted: bool) -> Result<Self, PropertyError> {
+        self.inverted = inverted;
+        self.convert_limits()
+    }
+
+    /// The servo angle at the joint's zero, which must be within one turn, and keep any limits
+    /// within the servo's range.
+    pub fn with_zero<T: Angle<f32>>(mut self, zero: T) -> Result<Self, PropertyError> {
+        let zero = zero.to_deg();
+        if !(0.0..360.0).contains(zero.as_value()) {
+            return Err(PropertyError::OutOfRange);
+        }
+        self.zero = zero;
+        self.convert_limits()
+    }
+
+    /// Limit goals to between the joint angles `min` and `max`, which must both be within the
+    /// servo's range.
+    ///
+    /// The limits stay at these joint angles when the ratio, direction or zero is changed
+    /// afterwards, so they can be set in any order.
+    pub fn with_limits<T: Angle<f32>>(
+        mut self,
+        min: T,
+        max: T,
+        policy: LimitPolicy,
+    ) -> Result<Self, PropertyError> {
+        let (min, max) = (min.to_deg(), max.to_deg());
+        if min.as_value() > max.as_value() {
+            return Err(PropertyError::MinNotBelowMax);
+        }
+
+        self.limits = Some((min, max, policy));
+        self.convert_limits()
+    }
+
+    /// Set the servo's soft limits from the joint limits, through the present conversion.
+    fn convert_limits(mut self) -> Result<Self, PropertyError> {
+        let Some((min, max, policy)) = self.limits else {
+            return Ok(self);
+        };
+
+        // Inverted joints reach their minimum at the servo's maximum.
+        let (min, max) = match self.inverted {
+            false => (self.position(min)?, self.position(max)?),
+            true => (self.position(max)?, self.position(min)?),
+        };
+        self.servo = self.servo.with_limits(SoftLimits::new(min, max, policy)?);
+        Ok(self)
+    }
+
+    pub fn servo(&self) -> &Servo {
+        &self.servo
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    pub fn zero(&self) -> Deg<f32> {
+        self.zero
+    }
+
+    /// The joint angles the servo can reach within its soft limits, as `(min, max)`.
+    pub fn range(&self) -> (Deg<f32>, Deg<f32>) {
+        let (min, max) = match self.servo.limits() {
+            Some(limits) => (limits.min(), limits.max()),
+            None => (
+                Position::new_raw(0).expect("Zero is a valid position."),
+                Position::new_raw(Position::MAX).expect("The maximum is a valid position."),
+            ),
+        };
+
+        let (min, max) = (self.angle(min), self.angle(max));
+        match self.inverted {
+            false => (min, max),
+            true => (max, min),
+        }
+    }
+
+    /// Servo units for each joint unit, with the sign of the joint's direction.
+    fn scale(&self) -> f32 {
+        match self.inverted {
+            false => self.ratio,
+            true => -self.ratio,
+        }
+    }
+
+    pub fn servo_angle<T: Angle<f32>>(&self, angle: T) -> Deg<f32> {
+        Deg(self.zero.as_value() + self.scale() * angle.to_deg().as_value())
+    }
+
+    pub fn joint_angle<T: Angle<f32>>(&self, servo_angle: T) -> Deg<f32> {
+        Deg((servo_angle.to_deg().as_value() - self.zero.as_value()) / self.scale())
+    }
+
+    /// The servo position for a joint angle, which must be within the servo's range.
+    pub fn position<T: Angle<f32>>(&self, angle: T) -> Result<Position, PropertyError> {
+        let steps = (self.servo_angle(angle).as_value() * STEPS_PER_TURN as f32 / 360.0).round();
+        if !(0.0..=Position::MAX as f32).contains(&steps) {
+            return Err(PropertyError::OutOfRange);
+        }
+        Position::new_raw(steps as u16)
+    }
+
+    pub fn angle(&self, position: Position) -> Deg<f32> {
+        self.joint_angle(position.to_deg())
+    }
+
+    /// How far the joint turns while its servo turns `steps`.
+    pub fn turned(&self, steps: f32) -> Deg<f32> {
+        Deg(steps * 360.0 / STEPS_PER_TURN as f32 / self.scale())
+    }
+
+    /// The signed servo speed in steps per second, for a joint velocity in degrees per second.
+    pub fn servo_velocity(&self, velocity: f32) -> f32 {
+        self.scale() * velocity * STEPS_PER_TURN as f32 / 360.0
+    }
+
+    /// The joint velocity in degrees per second, for a signed servo speed in steps per second.
+    pub fn velocity(&self, steps_per_second: i32) -> f32 {
+        steps_per_second as f32 * 360.0 / STEPS_PER_TURN as f32 / self.scale()
+    }
+
+    /// The goal speed for moving at `velocity` degrees per second, in either direction.
+    pub fn speed(&self, velocity: f32) -> Result<Speed, PropertyError> {
+        let steps = self.servo_velocity(velocity).abs().round();
+        if !(0.0..=u16::MAX as f32).contains(&steps) {
+            return Err(PropertyError::OutOfRange);
+        }
+        Speed::new_raw(steps as u16)
+    }
+
+    /// The wheel mode speed for turning at `velocity` degrees per second.
+    pub fn wheel_speed(&self, velocity: f32) -> Result<WheelSpeed, PropertyError> {
+        let steps = self.servo_velocity(velocity).round();
+        if !(i16::MIN as f32..=i16::MAX as f32).contains(&steps) {
+            return Err(PropertyError::OutOfRange);
+        }
+        WheelSpeed::new_raw(steps as i16)
+    }
+
+    /// The goal acceleration for `acceleration` degrees per second squared.
+    pub fn acceleration(&self, acceleration: f32) -> Result<Acceleration, PropertyError> {
+        let value = (self.servo_velocity(acceleration).abs() / 100.0).round();
+        if !(0.0..=u8::MAX as f32).contains(&value) {
+            return Err(PropertyError::OutOfRange);
+        }
+        Acceleration::new_raw(value as u8)
+    }
+
+    /// A position goal reaching `angle` at `velocity`, both in joint units. As with [`Speed`]
+    /// and [`Acceleration`], zero means the servo's maximum speed, or no acceleration.
+    ///
+    /// Soft limits are applied when the goal is written.
+    pub fn goal<T: Angle<f32>>(
+        &self,
+        angle: T,
+        velocity: f32,
+        acceleration: f32,
+    ) -> Result<Assign, PropertyError> {
+        Ok(Assign::new_position_goal(
+            self.position(angle)?,
+            self.speed(velocity)?,
+            self.acceleration(acceleration)?,
+        ))
+    }
+
+    /// Write a [`Joint::goal`] to the servo.
+    pub fn move_to<T: Angle<f32>>(
+        &self,
+        angle: T,
+        velocity: f32,
+        acceleration: f32,
+        port: &mut impl Port,
+    ) -> Result<(), ServoError> {
+        self.servo
+            .write(&self.goal(angle, velocity, acceleration)?, port)
+    }
+
+    /// Switch to wheel mode, and turn at `velocity` degrees per second.
+    pub fn spin(&self, velocity: f32, port: &mut impl Port) -> Result<(), ServoError> {
+        let assign = Assign::new()
+            .with(Mode::Wheel)
+            .with(self.wheel_speed(velocity)?);
+        self.servo.write(&assign, port)
+    }
+
+    pub fn read_angle(&self, port: &mut impl Port) -> Result<Deg<f32>, ServoError> {
+        Ok(self.angle(self.servo.read_position(port)?))
+    }
+
+    /// Read the joint's velocity in degrees per second.
+    pub fn read_velocity(&self, port: &mut impl Port) -> Result<f32, ServoError> {
+        Ok(self.velocity(self.servo.read_speed(port)?))
+    }
+
+    pub fn read_state(&self, port: &mut impl Port) -> Result<JointState, ServoError> {
+        Ok(self.state(&telemetry::read_sample(&self.servo, port)?))
+    }
+
+    /// Convert a sample of this joint's servo, such as one published by
+    /// [`Telemetry`](crate::telemetry::Telemetry).
+    pub fn state(&self, sample: &Sample) -> JointState {
+        JointState {
+            angle: self.angle(sample.position),
+            velocity: self.velocity(sample.speed),
+            load: match self.inverted {
+                false => sample.load,
+                true => -sample.load,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::{address, ID},
+        sim::Simulator,
+    };
+
+    fn joint() -> Joint {
+        Joint::new(Servo::new(ID::single(1).unwrap()))
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "Expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn converts_through_ratio_direction_and_zero() {
+        let joint = joint()
+            .with_ratio(2.0)
+            .and_then(|joint| joint.with_inverted(true))
+            .and_then(|joint| joint.with_zero(Deg(200.0)))
+            .unwrap();
+
+        assert_near(joint.servo_angle(Deg(10.0)).0, 180.0);
+        assert_near(joint.joint_angle(Deg(180.0)).0, 10.0);
+        assert_eq!(joint.position(Deg(10.0)).unwrap().value(), 2048);
+        assert_near(joint.angle(Position::new_raw(2048).unwrap()).0, 10.0);
+
+        // One servo turn is half a joint turn, the other way.
+        assert_near(joint.turned(4096.0).0, -180.0);
+        assert_near(joint.servo_velocity(90.0), -2048.0);
+        assert_near(joint.velocity(-2048), 90.0);
+
+        assert!(matches!(
+            joint.position(Deg(120.0)),
+            Err(PropertyError::OutOfRange)
+        ));
+    }
+
+    #[test]
+    fn wheel_speed_follows_the_direction() {
+        let forward = joint();
+        let backward = joint().with_inverted(true).unwrap();
+
+        assert_eq!(forward.wheel_speed(90.0).unwrap().value(), 1024);
+        assert_eq!(backward.wheel_speed(90.0).unwrap().value(), -1024);
+        // Goal speeds have no direction.
+        assert_eq!(backward.speed(90.0).unwrap().value(), 1024);
+    }
+
+    #[test]
+    fn state_is_in_joint_units() {
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::with_manual_clock();
+        let servo = sim.add_servo(id);
+        servo.set_position(3072.0);
+        servo.set_load(-250);
+
+        let joint = joint().with_inverted(true).unwrap();
+        let state = joint.read_state(&mut sim).unwrap();
+        assert_near(state.angle.0, -90.0);
+        // The servo heads back to its goal at full speed, turning the inverted joint forward.
+        assert_near(state.velocity, 3400.0 * 360.0 / 4096.0);
+        assert_eq!(state.load, 250);
+    }
+
+    #[test]
+    fn limits_follow_later_conversion_changes() {
+        let limited = |joint: Joint| {
+            joint
+                .with_limits(Deg(0.0), Deg(45.0), LimitPolicy::Reject)
+                .unwrap()
+        };
+
+        let before = limited(joint()).with_inverted(true).unwrap();
+        let after = limited(joint().with_inverted(true).unwrap());
+        for joint in [&before, &after] {
+            let (min, max) = joint.range();
+            assert_near(min.0, 0.0);
+            assert_near(max.0, 45.0);
+        }
+
+        let limits = before.servo().limits().unwrap();
+        assert_eq!((limits.min().value(), limits.max().value()), (1536, 2048));
+    }
+
+    #[test]
+    fn conversion_changes_keep_limits_in_range() {
+        let joint = joint()
+            .with_limits(Deg(-100.0), Deg(100.0), LimitPolicy::Clamp)
+            .unwrap();
+
+        assert!(matches!(
+            joint.clone().with_ratio(2.0),
+            Err(PropertyError::OutOfRange)
+        ));
+        assert!(matches!(
+            joint.with_zero(Deg(300.0)),
+            Err(PropertyError::OutOfRange)
+        ));
+    }
+
+    #[test]
+    fn goals_are_limited_in_joint_angles() {
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id);
+
+        let joint = joint()
+            .with_limits(Deg(0.0), Deg(45.0), LimitPolicy::Clamp)
+            .and_then(|joint| joint.with_inverted(true))
+            .unwrap();
+        joint.move_to(Deg(90.0), 0.0, 0.0, &mut sim).unwrap();
+
+        let goal = sim.servo(id).unwrap().word(address::GoalPosition);
+        assert_near(joint.angle(Position::new_raw(goal).unwrap()).0, 45.0);
+    }
+}
@@ -0,0 +1,594 @@
+use std::ops::Mul;
+
+use angle::Rad;
+use thiserror::Error;
+
+use crate::{
+    coordinated,
+    joint::Joint,
+    serial::Port,
+    servo::{self, Acceleration, Assign, Position, PropertyError, Servo, ServoError, Speed},
+};
+
+#[derive(Debug, Error)]
+pub enum KinematicsError {
+    #[error(
+        "No solution within tolerance. The closest is {position_error}m and {angle_error} radians away."
+    )]
+    Unreachable {
+        position_error: f32,
+        angle_error: f32,
+    },
+    #[error("The solver's {0} must be positive and finite.")]
+    Solver(&'static str),
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+}
+
+/// A rigid transform, as a homogeneous matrix stored by rows. Lengths are in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform(pub [[f32; 4]; 4]);
+
+impl Transform {
+    pub const IDENTITY: Self = Self([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    pub fn translation(x: f32, y: f32, z: f32) -> Self {
+        let mut transform = Self::IDENTITY;
+        transform.0[0][3] = x;
+        transform.0[1][3] = y;
+        transform.0[2][3] = z;
+        transform
+    }
+
+    pub fn from_parts(rotation: [[f32; 3]; 3], position: [f32; 3]) -> Self {
+        let mut transform = Self::IDENTITY;
+        for row in 0..3 {
+            transform.0[row][..3].copy_from_slice(&rotation[row]);
+            transform.0[row][3] = position[row];
+        }
+        transform
+    }
+
+    /// The transform across `link` when its joint is at `angle` radians.
+    pub fn from_link(link: &Link, angle: f32) -> Self {
+        let (sin, cos) = (link.theta + angle).sin_cos();
+        let (sin_alpha, cos_alpha) = link.alpha.sin_cos();
+        Self([
+            [cos, -sin * cos_alpha, sin * sin_alpha, link.a * cos],
+            [sin, cos * cos_alpha, -cos * sin_alpha, link.a * sin],
+            [0.0, sin_alpha, cos_alpha, link.d],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        [self.0[0][3], self.0[1][3], self.0[2][3]]
+    }
+
+    pub fn rotation(&self) -> [[f32; 3]; 3] {
+        [0, 1, 2].map(|row| [self.0[row][0], self.0[row][1], self.0[row][2]])
+    }
+
+    /// One of the rotated x, y or z axes.
+    fn axis(&self, column: usize) -> [f32; 3] {
+        [0, 1, 2].map(|row| self.0[row][column])
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self([0, 1, 2, 3].map(|row| {
+            [0, 1, 2, 3].map(|column| (0..4).map(|i| self.0[row][i] * other.0[i][column]).sum())
+        }))
+    }
+}
+
+/// One revolute link, in standard Denavit-Hartenberg parameters. The joint turns about the
+/// previous link's z axis, and its angle is added to `theta`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Link {
+    /// Along the previous z axis, in meters.
+    pub d: f32,
+    /// About the previous z axis, in radians.
+    pub theta: f32,
+    /// Along the new x axis, in meters.
+    pub a: f32,
+    /// About the new x axis, in radians.
+    pub alpha: f32,
+}
+
+/// Where the end of a chain should be.
+#[derive(Debug, Clone, Copy)]
+pub enum Target {
+    /// Only the position, for arms with too few joints to also choose their orientation.
+    Position([f32; 3]),
+    /// Both position and orientation.
+    Pose(Transform),
+}
+
+/// Settings for [`Chain::inverse`], which takes damped steps towards the target until it is
+/// within tolerance.
+#[derive(Debug, Clone, Copy)]
+pub struct Solver {
+    pub iterations: usize,
+    /// In meters.
+    pub tolerance: f32,
+    /// In radians, only for [`Target::Pose`].
+    pub angle_tolerance: f32,
+    /// Higher values are steadier near singular poses, but converge more slowly.
+    pub damping: f32,
+    /// The most any joint may turn in one iteration, in radians.
+    pub max_step: f32,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self {
+            iterations: 200,
+            tolerance: 1e-3,
+            angle_tolerance: 1e-2,
+            damping: 0.05,
+            max_step: 0.2,
+        }
+    }
+}
+
+impl Solver {
+    fn validate(&self) -> Result<(), KinematicsError> {
+        let settings = [
+            (self.tolerance, "tolerance"),
+            (self.angle_tolerance, "angle tolerance"),
+            (self.damping, "damping"),
+            (self.max_step, "max step"),
+        ];
+        match settings
+            .into_iter()
+            .find(|(value, _)| !(value.is_finite() && *value > 0.0))
+        {
+            Some((_, name)) => Err(KinematicsError::Solver(name)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A serial arm, from its base to its end, with a [`Joint`] turning each link.
+///
+/// Joint angles are in radians, at the joint, in the order of the links.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    links: Vec<(Link, Joint)>,
+    base: Transform,
+    tool: Transform,
+}
+
+impl Chain {
+    pub fn new(links: Vec<(Link, Joint)>) -> Self {
+        Self {
+            links,
+            base: Transform::IDENTITY,
+            tool: Transform::IDENTITY,
+        }
+    }
+
+    /// Where the first link is mounted, relative to the world.
+    pub fn with_base(mut self, base: Transform) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Where the tool is, relative to the last link.
+    pub fn with_tool(mut self, tool: Transform) -> Self {
+        self.tool = tool;
+        self
+    }
+
+    pub fn links(&self) -> &[(Link, Joint)] {
+        &self.links
+    }
+
+    /// The base, followed by the frame at the end of each link.
+    fn frames(&self, angles: &[f32]) -> Vec<Transform> {
+        assert_eq!(
+            angles.len(),
+            self.links.len(),
+            "Every joint needs an angle."
+        );
+
+        let mut frames = Vec::from([self.base]);
+        for ((link, _), &angle) in self.links.iter().zip(angles) {
+            let previous = *frames.last().expect("Frames start with the base.");
+            frames.push(previous * Transform::from_link(link, angle));
+        }
+        frames
+    }
+
+    /// Where the tool is when the joints are at `angles`.
+    ///
+    /// # Panics
+    ///
+    /// When the number of angles does not match the number of links.
+    pub fn forward(&self, angles: &[f32]) -> Transform {
+        *self
+            .frames(angles)
+            .last()
+            .expect("Frames start with the base.")
+            * self.tool
+    }
+
+    /// The angles each joint can reach within its soft limits, as `(min, max)`.
+    pub fn ranges(&self) -> Vec<(f32, f32)> {
+        self.links
+            .iter()
+            .map(|(_, joint)| {
+                let (min, max) = joint.range();
+                (min.0.to_radians(), max.0.to_radians())
+            })
+            .collect()
+    }
+
+    pub fn angles(&self, positions: &[Position]) -> Vec<f32> {
+        self.links
+            .iter()
+            .zip(positions)
+            .map(|((_, joint), &position)| joint.angle(position).0.to_radians())
+            .collect()
+    }
+
+    pub fn positions(&self, angles: &[f32]) -> Result<Vec<Position>, PropertyError> {
+        self.links
+            .iter()
+            .zip(angles)
+            .map(|((_, joint), &angle)| joint.position(Rad(angle)))
+            .collect()
+    }
+
+    /// Joint angles that put the tool at `target`, starting the search from `seed`, usually
+    /// the present angles. Every angle is kept within its joint's soft limits.
+    ///
+    /// The solver's tolerances, damping and max step must all be positive.
+    ///
+    /// # Panics
+    ///
+    /// When the number of seed angles does not match the number of links.
+    pub fn inverse(
+        &self,
+        target: Target,
+        seed: &[f32],
+        solver: &Solver,
+    ) -> Result<Vec<f32>, KinematicsError> {
+        solver.validate()?;
+        let ranges = self.ranges();
+        let clamp = |angles: &mut [f32]| {
+            for (angle, &(min, max)) in angles.iter_mut().zip(&ranges) {
+                *angle = angle.clamp(min, max);
+            }
+        };
+
+        let mut angles = Vec::from(seed);
+        clamp(&mut angles);
+        let mut closest = (f32::INFINITY, f32::INFINITY);
+
+        for iteration in 0..=solver.iterations {
+            let frames = self.frames(&angles);
+            let end = *frames.last().expect("Frames start with the base.") * self.tool;
+            let (error, position_error, angle_error) = target_error(&target, &end);
+
+            if position_error <= solver.tolerance && angle_error <= solver.angle_tolerance {
+                return Ok(angles);
+            }
+            if position_error + angle_error < closest.0 + closest.1 {
+                closest = (position_error, angle_error);
+            }
+            if iteration == solver.iterations {
+                break;
+            }
+
+            // Each joint turns the end about its own axis, which is the previous frame's z.
+            let jacobian: Vec<Vec<f32>> = frames[..self.links.len()]
+                .iter()
+                .map(|frame| {
+                    let axis = frame.axis(2);
+                    let lever = sub(end.position(), frame.position());
+                    let mut column = Vec::from(cross(axis, lever));
+                    if error.len() == 6 {
+                        column.extend(axis);
+                    }
+                    column
+                })
+                .collect();
+
+            let mut step = damped_least_squares(&jacobian, &error, solver.damping);
+            let largest = step.iter().fold(0.0f32, |largest, x| largest.max(x.abs()));
+            if largest > solver.max_step {
+                step.iter_mut()
+                    .for_each(|x| *x *= solver.max_step / largest);
+            }
+
+            angles
+                .iter_mut()
+                .zip(&step)
+                .for_each(|(angle, x)| *angle += x);
+            clamp(&mut angles);
+        }
+
+        Err(KinematicsError::Unreachable {
+            position_error: closest.0,
+            angle_error: closest.1,
+        })
+    }
+
+    pub fn read_angles(&self, port: &mut impl Port) -> Result<Vec<f32>, ServoError> {
+        let positions = self.read_positions(port)?;
+        Ok(self.angles(&positions))
+    }
+
+    /// Read where the tool is.
+    pub fn read_pose(&self, port: &mut impl Port) -> Result<Transform, ServoError> {
+        Ok(self.forward(&self.read_angles(port)?))
+    }
+
+    fn read_positions(&self, port: &mut impl Port) -> Result<Vec<Position>, ServoError> {
+        self.links
+            .iter()
+            .map(|(_, joint)| joint.servo().read_position(port))
+            .collect()
+    }
+
+    /// Solve for `target` from the present angles, then move every joint there with one sync
+    /// write, so they all arrive together. See [`coordinated::plan`].
+    ///
+    /// Returns the angles moved to.
+    pub fn move_to(
+        &self,
+        target: Target,
+        solver: &Solver,
+        speed: Speed,
+        acceleration: Acceleration,
+        port: &mut impl Port,
+    ) -> Result<Vec<f32>, KinematicsError> {
+        let starts = self.read_positions(port)?;
+        let angles = self.inverse(target, &self.angles(&starts), solver)?;
+        let ends = self.positions(&angles).map_err(ServoError::from)?;
+
+        let moves: Vec<(Position, Position)> = starts.into_iter().zip(ends).collect();
+        let assigns = coordinated::plan(&moves, speed, acceleration);
+        let writes: Vec<(&Servo, &Assign)> = self
+            .links
+            .iter()
+            .map(|(_, joint)| joint.servo())
+            .zip(&assigns)
+            .collect();
+        servo::sync_write(&writes, port)?;
+
+        Ok(angles)
+    }
+}
+
+/// The error to correct, as the position error followed by any orientation error, and the
+/// distance and angle to the target.
+fn target_error(target: &Target, end: &Transform) -> (Vec<f32>, f32, f32) {
+    let goal = match target {
+        Target::Position(position) => *position,
+        Target::Pose(pose) => pose.position(),
+    };
+    let position_error = sub(goal, end.position());
+    let mut error = Vec::from(position_error);
+
+    let angle_error = match target {
+        Target::Position(_) => 0.0,
+        Target::Pose(pose) => {
+            // Half the sum of each present axis crossed with its goal turns one onto the other.
+            let mut turn = [0.0; 3];
+            for column in 0..3 {
+                let axis = cross(end.axis(column), pose.axis(column));
+                turn = [0, 1, 2].map(|i| turn[i] + axis[i] / 2.0);
+            }
+            error.extend(turn);
+
+            let trace: f32 = (0..3)
+                .map(|column| dot(end.axis(column), pose.axis(column)))
+                .sum();
+            ((trace - 1.0) / 2.0).clamp(-1.0, 1.0).acos()
+        }
+    };
+
+    (error, norm(position_error), angle_error)
+}
+
+/// The joint step that best reduces `error`, as `J^T (J J^T + damping^2 I)^-1 error`, where
+/// `jacobian` holds one column for each joint.
+fn damped_least_squares(jacobian: &[Vec<f32>], error: &[f32], damping: f32) -> Vec<f32> {
+    let rows = error.len();
+    let mut matrix: Vec<Vec<f32>> = (0..rows)
+        .map(|row| {
+            (0..rows)
+                .map(|column| {
+                    let product: f32 = jacobian
+                        .iter()
+                        .map(|joint| joint[row] * joint[column])
+                        .sum();
+                    match row == column {
+                        true => product + damping.powi(2),
+                        false => product,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let weights = solve(&mut matrix, Vec::from(error));
+    jacobian
+        .iter()
+        .map(|joint| joint.iter().zip(&weights).map(|(j, w)| j * w).sum())
+        .collect()
+}
+
+/// Solve `matrix * x = vector` by Gaussian elimination. The matrix must not be singular, which
+/// damping guarantees.
+fn solve(matrix: &mut [Vec<f32>], mut vector: Vec<f32>) -> Vec<f32> {
+    let size = vector.len();
+    for pivot in 0..size {
+        let best = (pivot..size)
+            .max_by(|&a, &b| matrix[a][pivot].abs().total_cmp(&matrix[b][pivot].abs()))
+            .expect("Rows remain below the pivot.");
+        matrix.swap(pivot, best);
+        vector.swap(pivot, best);
+
+        for row in pivot + 1..size {
+            let factor = matrix[row][pivot] / matrix[pivot][pivot];
+            let pivot_row = matrix[pivot].clone();
+            for (value, above) in matrix[row].iter_mut().zip(&pivot_row).skip(pivot) {
+                *value -= factor * above;
+            }
+            vector[row] -= factor * vector[pivot];
+        }
+    }
+
+    let mut x = vec![0.0; size];
+    for row in (0..size).rev() {
+        let known: f32 = (row + 1..size)
+            .map(|column| matrix[row][column] * x[column])
+            .sum();
+        x[row] = (vector[row] - known) / matrix[row][row];
+    }
+    x
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use angle::Deg;
+
+    use super::*;
+    use crate::{hardware::ID, servo::LimitPolicy};
+
+    /// A planar arm of two 0.1m links turning about z, each joint limited to `(min, max)`
+    /// degrees.
+    fn planar_arm(limits: [(f32, f32); 2]) -> Chain {
+        let links = limits
+            .into_iter()
+            .zip(1..)
+            .map(|((min, max), id)| {
+                let joint = Joint::new(Servo::new(ID::single(id).unwrap()))
+                    .with_limits(Deg(min), Deg(max), LimitPolicy::Reject)
+                    .unwrap();
+                let link = Link {
+                    a: 0.1,
+                    ..Link::default()
+                };
+                (link, joint)
+            })
+            .collect();
+        Chain::new(links)
+    }
+
+    fn assert_near(actual: [f32; 3], expected: [f32; 3]) {
+        let distance = norm(sub(actual, expected));
+        assert!(distance < 2e-3, "Expected {expected:?}, got {actual:?}");
+    }
+
+    #[test]
+    fn forward_follows_each_link() {
+        let arm = planar_arm([(-180.0, 179.0); 2]);
+
+        assert_near(arm.forward(&[0.0, 0.0]).position(), [0.2, 0.0, 0.0]);
+        assert_near(arm.forward(&[FRAC_PI_2, 0.0]).position(), [0.0, 0.2, 0.0]);
+        assert_near(arm.forward(&[0.0, FRAC_PI_2]).position(), [0.1, 0.1, 0.0]);
+
+        let moved = arm.with_base(Transform::translation(0.0, 0.0, 0.5));
+        assert_near(moved.forward(&[0.0, 0.0]).position(), [0.2, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn inverse_stays_within_soft_limits() {
+        // The seed is already a solution, but only bending the elbow the other way is allowed.
+        let arm = planar_arm([(-180.0, 179.0), (20.0, 135.0)]);
+        let target = [0.1, 0.1, 0.0];
+
+        let angles = arm
+            .inverse(
+                Target::Position(target),
+                &[FRAC_PI_2, -FRAC_PI_2],
+                &Solver::default(),
+            )
+            .unwrap();
+
+        for (angle, (min, max)) in angles.iter().zip(arm.ranges()) {
+            assert!((min..=max).contains(angle), "{angles:?}");
+        }
+        assert_near(arm.forward(&angles).position(), target);
+    }
+
+    #[test]
+    fn inverse_reports_targets_outside_soft_limits() {
+        let arm = planar_arm([(0.0, 10.0), (-180.0, 179.0)]);
+        let result = arm.inverse(
+            Target::Position([0.0, 0.2, 0.0]),
+            &[0.0, 0.0],
+            &Solver::default(),
+        );
+        assert!(matches!(result, Err(KinematicsError::Unreachable { .. })));
+    }
+
+    #[test]
+    fn inverse_rejects_invalid_solvers() {
+        let arm = planar_arm([(-180.0, 179.0); 2]);
+        let target = Target::Pose(Transform::translation(0.1, 0.1, 0.0));
+        let solve = |solver| arm.inverse(target, &[0.0, 0.0], &solver);
+
+        let damping = Solver {
+            damping: 0.0,
+            ..Solver::default()
+        };
+        assert!(matches!(
+            solve(damping),
+            Err(KinematicsError::Solver("damping"))
+        ));
+
+        let max_step = Solver {
+            max_step: -1.0,
+            ..Solver::default()
+        };
+        assert!(matches!(
+            solve(max_step),
+            Err(KinematicsError::Solver("max step"))
+        ));
+
+        let tolerance = Solver {
+            tolerance: f32::NAN,
+            ..Solver::default()
+        };
+        assert!(matches!(
+            solve(tolerance),
+            Err(KinematicsError::Solver("tolerance"))
+        ));
+    }
+}
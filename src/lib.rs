@@ -1,8 +1,28 @@
+pub mod bus;
+pub mod calibration;
 pub mod command;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod coordinated;
+pub mod drive;
+pub mod gait;
 pub mod hardware;
+pub mod joint;
+pub mod kinematics;
+pub mod odometry;
+pub mod replay;
 pub mod response;
 pub mod serial;
 pub mod servo;
+pub mod sim;
+pub mod snapshot;
+pub mod sniff;
+pub mod supervisor;
+pub mod teach;
+pub mod telemetry;
+mod text;
+pub mod trajectory;
+pub mod wait;
 
 fn crc(packet: &[u8]) -> u8 {
     !packet.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
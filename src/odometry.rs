@@ -0,0 +1,222 @@
+use crate::{
+    drive::{DriveError, Kinematics, Pose},
+    joint::Joint,
+    serial::Port,
+    servo::{Position, ServoError, STEPS_PER_TURN},
+};
+
+/// Tracks how far each wheel has travelled by reading `PresentPosition`, counting the turns it
+/// rolls over between reads, and estimates the body's pose from those distances.
+///
+/// Each wheel must turn less than half a turn between updates, or its turns are miscounted. At
+/// the servo's top speed of about one turn per second, update at least every 0.4 seconds.
+#[derive(Debug, Clone)]
+pub struct Odometry {
+    kinematics: Kinematics,
+    wheels: Vec<Joint>,
+    /// The position each wheel was last read at, until the first update.
+    last: Option<Vec<Position>>,
+    /// Steps each wheel's servo has turned since odometry started.
+    steps: Vec<i64>,
+    pose: Pose,
+}
+
+impl Odometry {
+    /// `wheels` are in the order given by `kinematics`. See [`Drive::odometry`] to track a
+    /// drive's wheels.
+    ///
+    /// [`Drive::odometry`]: crate::drive::Drive::odometry
+    pub fn new(kinematics: Kinematics, wheels: Vec<Joint>) -> Result<Self, DriveError> {
+        let kinematics = kinematics.validate()?;
+        if wheels.len() != kinematics.wheel_count() {
+            return Err(DriveError::WheelCount {
+                expected: kinematics.wheel_count(),
+                received: wheels.len(),
+            });
+        }
+
+        Ok(Self {
+            kinematics,
+            steps: vec![0; wheels.len()],
+            wheels,
+            last: None,
+            pose: Pose::default(),
+        })
+    }
+
+    /// Read every wheel's position, then see [`Odometry::update_with`].
+    ///
+    /// When any read fails, nothing is updated, and the next update covers both intervals.
+    pub fn update(&mut self, port: &mut impl Port) -> Result<Pose, ServoError> {
+        let positions = self
+            .wheels
+            .iter()
+            .map(|wheel| wheel.servo().read_position(port))
+            .collect::<Result<Vec<Position>, ServoError>>()?;
+        Ok(self.update_with(&positions))
+    }
+
+    /// Add how far each wheel has turned since the last update, given its present position,
+    /// such as from a [`Sample`](crate::telemetry::Sample). The first update only records where
+    /// the wheels start.
+    ///
+    /// # Panics
+    ///
+    /// When the number of positions does not match the number of wheels.
+    pub fn update_with(&mut self, positions: &[Position]) -> Pose {
+        assert_eq!(
+            positions.len(),
+            self.wheels.len(),
+            "Every wheel needs a position."
+        );
+
+        let Some(last) = self.last.replace(positions.to_vec()) else {
+            return self.pose;
+        };
+
+        let moved: Vec<f32> = self
+            .wheels
+            .iter()
+            .zip(positions.iter().zip(&last))
+            .zip(&mut self.steps)
+            .map(|((wheel, (position, last)), steps)| {
+                let change = unwrap(last.value(), position.value());
+                *steps += change as i64;
+                wheel.turned(change as f32).0.to_radians()
+            })
+            .collect();
+
+        // The turn of each wheel over the interval is a twist lasting one unit of time.
+        let twist = self
+            .kinematics
+            .twist(&moved)
+            .expect("Odometry has a wheel for each of its kinematics.");
+        self.pose.integrate(twist, 1.0);
+        self.pose
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    /// How far each wheel has turned since odometry started, in radians, positive forward.
+    pub fn angles(&self) -> Vec<f32> {
+        self.wheels
+            .iter()
+            .zip(&self.steps)
+            .map(|(wheel, &steps)| wheel.turned(steps as f32).0.to_radians())
+            .collect()
+    }
+
+    /// How far each wheel's rim has rolled since odometry started, in meters, positive
+    /// forward.
+    pub fn distances(&self) -> Vec<f32> {
+        let radius = self.kinematics.wheel_radius();
+        self.angles()
+            .into_iter()
+            .map(|angle| angle * radius)
+            .collect()
+    }
+
+    /// Start again from the origin, with the wheels where they were last read.
+    pub fn reset(&mut self) {
+        self.steps.fill(0);
+        self.pose = Pose::default();
+    }
+}
+
+/// The signed steps from `last` to `present`, taking the shorter way around the rollover.
+fn unwrap(last: u16, present: u16) -> i32 {
+    let (change, turn) = (present as i32 - last as i32, STEPS_PER_TURN as i32);
+    (change + turn / 2).rem_euclid(turn) - turn / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        drive::{Drive, Twist},
+        hardware::ID,
+        servo::Servo,
+        sim::Simulator,
+    };
+
+    /// Drive a differential base straight at `x` meters per second for one second, with the
+    /// left wheel starting at `left` and the mirrored right wheel at `right`.
+    fn drive_straight(x: f32, left: f32, right: f32) -> Odometry {
+        let ids = [1, 2].map(|id| ID::single(id).unwrap());
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ids[0]).set_position(left);
+        sim.add_servo(ids[1]).set_position(right);
+
+        let wheels = Vec::from([
+            Joint::new(Servo::new(ids[0])),
+            Joint::new(Servo::new(ids[1])).with_inverted(true).unwrap(),
+        ]);
+        let kinematics = Kinematics::differential(0.03, 0.2).unwrap();
+        let drive = Drive::new(kinematics, wheels).unwrap();
+        let mut odometry = drive.odometry();
+
+        drive.enable(&mut sim).unwrap();
+        odometry.update(&mut sim).unwrap();
+        drive
+            .set_twist(
+                Twist {
+                    x,
+                    ..Twist::default()
+                },
+                &mut sim,
+            )
+            .unwrap();
+        for _ in 0..10 {
+            sim.step(Duration::from_millis(100));
+            odometry.update(&mut sim).unwrap();
+        }
+
+        odometry
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.002,
+            "Expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn counts_turns_across_the_rollover_forward() {
+        // The left wheel rolls over from 4095 to 0, and the right from 0 to 4095.
+        let odometry = drive_straight(0.1, 4000.0, 100.0);
+
+        for distance in odometry.distances() {
+            assert_near(distance, 0.1);
+        }
+        let pose = odometry.pose();
+        assert_near(pose.x, 0.1);
+        assert_near(pose.y, 0.0);
+        assert_near(pose.heading, 0.0);
+    }
+
+    #[test]
+    fn counts_turns_across_the_rollover_backward() {
+        // The left wheel rolls over from 0 to 4095, and the right from 4095 to 0.
+        let odometry = drive_straight(-0.1, 100.0, 4000.0);
+
+        for distance in odometry.distances() {
+            assert_near(distance, -0.1);
+        }
+        let pose = odometry.pose();
+        assert_near(pose.x, -0.1);
+        assert_near(pose.y, 0.0);
+        assert_near(pose.heading, 0.0);
+    }
+
+    #[test]
+    fn unwrap_takes_the_shorter_way() {
+        assert_eq!(unwrap(4000, 100), 196);
+        assert_eq!(unwrap(100, 4000), -196);
+        assert_eq!(unwrap(1000, 1200), 200);
+    }
+}
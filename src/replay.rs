@@ -0,0 +1,390 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, ErrorKind, Read, Write},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::text;
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("IO Error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Capture format is invalid on line {0}.")]
+    Format(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exchange {
+    /// Bytes written by the host.
+    Transmit(Vec<u8>),
+    /// Bytes read by the host.
+    Receive(Vec<u8>),
+    /// A read that failed, such as a timeout when a servo did not answer.
+    ReadError(ErrorKind),
+}
+
+/// One read or write, and when it happened since the start of the session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub time: Duration,
+    pub exchange: Exchange,
+}
+
+/// Everything that passed through a port during a session.
+#[derive(Debug, Clone, Default)]
+pub struct Capture {
+    pub events: Vec<Event>,
+}
+
+impl Capture {
+    const HEADER: &'static str = "waveshare_serial_servo capture v1";
+
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), CaptureError> {
+        writeln!(writer, "{}", Self::HEADER)?;
+        for event in &self.events {
+            write_event(&mut writer, event)?;
+        }
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, CaptureError> {
+        let events = text::lines_after(reader, Self::HEADER)?
+            .ok_or(CaptureError::Format(1))?
+            .map(|line| {
+                let (number, line) = line?;
+                parse_event(&line).ok_or(CaptureError::Format(number))
+            })
+            .collect::<Result<Vec<Event>, CaptureError>>()?;
+
+        Ok(Self { events })
+    }
+}
+
+fn write_event<W: Write>(writer: &mut W, event: &Event) -> io::Result<()> {
+    write!(writer, "{}", event.time.as_micros())?;
+    let bytes = match &event.exchange {
+        Exchange::Transmit(bytes) => {
+            write!(writer, " tx")?;
+            bytes.as_slice()
+        }
+        Exchange::Receive(bytes) => {
+            write!(writer, " rx")?;
+            bytes.as_slice()
+        }
+        Exchange::ReadError(kind) => {
+            write!(writer, " err {}", error_name(*kind))?;
+            &[]
+        }
+    };
+    for byte in bytes {
+        write!(writer, " {byte:02x}")?;
+    }
+    writeln!(writer)
+}
+
+fn parse_event(line: &str) -> Option<Event> {
+    let mut fields = line.split_whitespace();
+    let time = Duration::from_micros(fields.next()?.parse().ok()?);
+
+    let kind = fields.next()?;
+    let rest: Vec<&str> = fields.collect();
+    let bytes = || {
+        rest.iter()
+            .map(|byte| u8::from_str_radix(byte, 16).ok())
+            .collect::<Option<Vec<u8>>>()
+    };
+    let exchange = match (kind, rest.as_slice()) {
+        ("tx", _) => Exchange::Transmit(bytes()?),
+        ("rx", _) => Exchange::Receive(bytes()?),
+        ("err", &[name]) => Exchange::ReadError(error_kind(name)?),
+        _ => return None,
+    };
+
+    Some(Event { time, exchange })
+}
+
+/// The errors a serial port is expected to produce. Anything else is saved as `other`.
+const ERROR_NAMES: [(ErrorKind, &str); 5] = [
+    (ErrorKind::TimedOut, "timed_out"),
+    (ErrorKind::WouldBlock, "would_block"),
+    (ErrorKind::Interrupted, "interrupted"),
+    (ErrorKind::UnexpectedEof, "unexpected_eof"),
+    (ErrorKind::BrokenPipe, "broken_pipe"),
+];
+
+fn error_name(kind: ErrorKind) -> &'static str {
+    ERROR_NAMES
+        .iter()
+        .find(|&&(known, _)| known == kind)
+        .map_or("other", |&(_, name)| name)
+}
+
+fn error_kind(name: &str) -> Option<ErrorKind> {
+    match name {
+        "other" => Some(ErrorKind::Other),
+        name => ERROR_NAMES
+            .iter()
+            .find(|&&(_, known)| known == name)
+            .map(|&(kind, _)| kind),
+    }
+}
+
+/// A port that writes every read and write passing through it to a capture file, as it
+/// happens, so the session survives a crash. Load the file with [`Capture::load`] and serve it
+/// back with [`Replay`].
+///
+/// As with a [`Sniffer`](crate::sniff::Sniffer) log, a failed capture write is kept for
+/// [`Recorder::take_capture_error`] rather than failing the port.
+#[derive(Debug)]
+pub struct Recorder<P, W: Write> {
+    port: P,
+    writer: W,
+    start: Instant,
+    capture_error: Option<io::Error>,
+}
+
+impl<P, W: Write> Recorder<P, W> {
+    pub fn new(port: P, mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{}", Capture::HEADER)?;
+        Ok(Self {
+            port,
+            writer,
+            start: Instant::now(),
+            capture_error: None,
+        })
+    }
+
+    pub fn into_inner(self) -> (P, W) {
+        (self.port, self.writer)
+    }
+
+    /// The first error writing the capture since the last call, if any.
+    pub fn take_capture_error(&mut self) -> Option<io::Error> {
+        self.capture_error.take()
+    }
+
+    fn record(&mut self, exchange: Exchange) {
+        let event = Event {
+            time: self.start.elapsed(),
+            exchange,
+        };
+        if let Err(e) = write_event(&mut self.writer, &event) {
+            self.capture_error.get_or_insert(e);
+        }
+    }
+}
+
+impl<P: Read, W: Write> Read for Recorder<P, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.port.read(buf) {
+            Ok(count) => {
+                self.record(Exchange::Receive(Vec::from(&buf[..count])));
+                Ok(count)
+            }
+            Err(e) => {
+                self.record(Exchange::ReadError(e.kind()));
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<P: Write, W: Write> Write for Recorder<P, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.port.write(buf)?;
+        self.record(Exchange::Transmit(Vec::from(&buf[..count])));
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()?;
+        self.writer.flush()
+    }
+}
+
+/// A port that plays back a [`Capture`], for reproducing a session without hardware.
+///
+/// Reads are served from the captured responses, and every write is checked against what was
+/// captured. Bytes may be read and written in different sized pieces than they were captured
+/// in, but not out of order. Event times are ignored.
+///
+/// # Panics
+///
+/// Reading or writing panics when it does not match the capture, or when the capture has run
+/// out, so a replay fails the test it is used in as soon as the code under test diverges.
+#[derive(Debug)]
+pub struct Replay {
+    events: VecDeque<Exchange>,
+    /// How many events have been fully used, for error messages.
+    position: usize,
+}
+
+impl Replay {
+    pub fn new(capture: Capture) -> Self {
+        Self {
+            events: capture
+                .events
+                .into_iter()
+                .map(|event| event.exchange)
+                .filter(|exchange| match exchange {
+                    Exchange::Transmit(bytes) | Exchange::Receive(bytes) => !bytes.is_empty(),
+                    Exchange::ReadError(_) => true,
+                })
+                .collect(),
+            position: 0,
+        }
+    }
+
+    /// Whether every captured event has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Remove the first `count` bytes of the front event, which holds at least that many.
+    fn consume(&mut self, count: usize) {
+        if let Some(Exchange::Transmit(bytes) | Exchange::Receive(bytes)) = self.events.front_mut()
+        {
+            bytes.drain(..count);
+            if bytes.is_empty() {
+                self.advance();
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        self.events.pop_front();
+        self.position += 1;
+    }
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+        match self.events.front() {
+            Some(Exchange::Receive(bytes)) => {
+                let count = buf.len().min(bytes.len());
+                buf[..count].copy_from_slice(&bytes[..count]);
+                self.consume(count);
+                Ok(count)
+            }
+            Some(&Exchange::ReadError(kind)) => {
+                self.advance();
+                Err(kind.into())
+            }
+            Some(Exchange::Transmit(bytes)) => {
+                panic!("Read at event {position}, but the capture expects a write of {bytes:02x?}.")
+            }
+            None => panic!("Read after the capture ended, at event {position}."),
+        }
+    }
+}
+
+impl Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let position = self.position;
+            let Some(Exchange::Transmit(expected)) = self.events.front() else {
+                panic!(
+                    "Wrote {:02x?} at event {position}, but the capture expects {:?}.",
+                    &buf[written..],
+                    self.events.front(),
+                );
+            };
+
+            let count = expected.len().min(buf.len() - written);
+            let actual = &buf[written..written + count];
+            assert_eq!(
+                actual,
+                &expected[..count],
+                "Written bytes differ from the capture at event {position}."
+            );
+
+            self.consume(count);
+            written += count;
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::{address, ID},
+        servo::{Assign, Position, Servo, ServoError},
+        sim::Simulator,
+    };
+
+    /// Write a goal, read back the position, and ping a servo that is not there.
+    fn session(port: &mut (impl Read + Write)) -> (u16, bool) {
+        let servo = Servo::new(ID::single(1).unwrap());
+        let goal = Assign::new().with(Position::new_raw(1000).unwrap());
+        servo.write(&goal, port).unwrap();
+        let position = servo.read_word(address::GoalPosition, port);
+
+        let missing = Servo::new(ID::single(2).unwrap()).ping(port);
+        (
+            position.unwrap(),
+            matches!(missing, Err(ServoError::Serial(_))),
+        )
+    }
+
+    #[test]
+    fn replays_a_recorded_session() {
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ID::single(1).unwrap());
+
+        let mut recorder = Recorder::new(sim, Vec::new()).unwrap();
+        let recorded = session(&mut recorder);
+        assert!(recorder.take_capture_error().is_none());
+        let (_, file) = recorder.into_inner();
+
+        let capture = Capture::load(file.as_slice()).unwrap();
+        let mut saved = Vec::new();
+        capture.save(&mut saved).unwrap();
+        assert_eq!(saved, file);
+
+        let mut replay = Replay::new(capture);
+        assert_eq!(session(&mut replay), recorded);
+        assert_eq!(recorded, (1000, true));
+        assert!(replay.is_finished());
+    }
+
+    /// A capture file with room for only so many more bytes.
+    struct Full(usize);
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.0 {
+                return Err(io::ErrorKind::StorageFull.into());
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_full_capture_does_not_fail_the_session() {
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ID::single(1).unwrap());
+
+        let mut recorder = Recorder::new(sim, Full(Capture::HEADER.len() + 1)).unwrap();
+        assert_eq!(session(&mut recorder), (1000, true));
+
+        let error = recorder.take_capture_error().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+    }
+}
@@ -6,61 +6,250 @@ use crate::{
 };
 
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "ResponseFields")
+)]
 pub struct Response {
     pub id: ID,
     pub errors: Option<DriverErrors>,
     pub payload: Vec<u8>,
 }
 
-#[derive(Debug, Error)]
+/// A response before checking it could have come from a servo.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ResponseFields {
+    id: ID,
+    errors: Option<DriverErrors>,
+    payload: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ResponseFields> for Response {
+    type Error = ResponseError;
+
+    fn try_from(fields: ResponseFields) -> Result<Self, Self::Error> {
+        if fields.id == ID::Broadcast {
+            return Err(ResponseError::IdInvalid(fields.id.value()));
+        }
+
+        // The length field counts the payload, status and crc in one byte.
+        if fields.payload.len() > Self::MAX_PAYLOAD {
+            return Err(ResponseError::PayloadTooLong(fields.payload.len()));
+        }
+
+        Ok(Self {
+            id: fields.id,
+            errors: fields.errors,
+            payload: fields.payload,
+        })
+    }
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
 pub enum ResponseError {
-    #[error("Response format is invalid.")]
-    Malformed,
-    #[error("CRC does not match.")]
-    CrcInvalid,
-    #[error("The id is not valid.")]
-    IdInvalid,
+    #[error("Response is {received} bytes, but at least {expected} are needed.")]
+    Truncated { expected: usize, received: usize },
+    #[error("Response does not start with the 0xff 0xff header.")]
+    Header,
+    #[error("Response length field {0} is too short to hold the status and crc.")]
+    LengthTooShort(u8),
+    #[error("Response is {received} bytes, but its length field declares {expected}.")]
+    TrailingBytes { expected: usize, received: usize },
+    #[error("The id {0:#04x} is not valid for a response.")]
+    IdInvalid(u8),
+    #[error("CRC {received:#04x} does not match the calculated {calculated:#04x}.")]
+    CrcInvalid { calculated: u8, received: u8 },
+    #[error("A payload of {0} bytes does not fit in one response.")]
+    PayloadTooLong(usize),
+}
+
+impl Response {
+    /// Bytes before the length field's count begins: the header, id and length itself.
+    const PREFIX: usize = 4;
+    /// The status byte and crc, which the length field always counts.
+    const MIN_LENGTH: u8 = 2;
+    /// The most payload the length field can count, alongside the status and crc.
+    #[cfg(feature = "serde")]
+    const MAX_PAYLOAD: usize = (u8::MAX - Self::MIN_LENGTH) as usize;
 }
 
 impl TryFrom<&[u8]> for Response {
     type Error = ResponseError;
 
+    /// Decode exactly one packet, `[0xff, 0xff, id, length, status, payload.., crc]`, where
+    /// `length` counts the status, payload and crc.
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let id = value.get(2);
-        let length = value.get(3);
-        let error = value.get(4);
-
-        // Make sure the packet has the required fields.
-        let (Some(&id), Some(&length), Some(&error)) = (id, length, error) else {
-            return Err(ResponseError::Malformed);
+        let &[header_0, header_1, id, length, ref rest @ ..] = value else {
+            return Err(ResponseError::Truncated {
+                expected: Self::PREFIX,
+                received: value.len(),
+            });
         };
 
-        // Make sure the id is valid
-        let Ok(id) = ID::try_from(id) else {
-            return Err(ResponseError::IdInvalid);
-        };
+        if [header_0, header_1] != [0xff, 0xff] {
+            return Err(ResponseError::Header);
+        }
 
-        // Make sure the crc is present.
-        let Some(&rx_crc) = value.get(3 + length as usize) else {
-            return Err(ResponseError::Malformed);
-        };
+        if length < Self::MIN_LENGTH {
+            return Err(ResponseError::LengthTooShort(length));
+        }
+
+        let expected = Self::PREFIX + length as usize;
+        if rest.len() < length as usize {
+            return Err(ResponseError::Truncated {
+                expected,
+                received: value.len(),
+            });
+        }
+        if rest.len() > length as usize {
+            return Err(ResponseError::TrailingBytes {
+                expected,
+                received: value.len(),
+            });
+        }
 
-        // Make sure the crc is valid.
-        let crc = crc(&value[2..value.len() - 1]);
-        if rx_crc != crc {
-            return Err(ResponseError::CrcInvalid);
+        // The crc covers everything after the header, up to the crc itself.
+        let (&received, _) = rest.split_last().expect("Length is at least two.");
+        let calculated = crc(&value[2..expected - 1]);
+        if received != calculated {
+            return Err(ResponseError::CrcInvalid {
+                calculated,
+                received,
+            });
         }
 
-        // Parse the errors.
-        let error = DriverErrors::from_byte(error);
+        let id = match ID::try_from(id) {
+            Ok(ID::Single(id)) => ID::Single(id),
+            _ => return Err(ResponseError::IdInvalid(id)),
+        };
 
-        // Collect the payload.
-        let payload = Vec::from(&value[5..value.len() - 1]);
+        let (&status, payload) = rest[..rest.len() - 1]
+            .split_first()
+            .expect("Length is at least two.");
 
         Ok(Self {
             id,
-            errors: error,
-            payload,
+            errors: DriverErrors::from_byte(status),
+            payload: Vec::from(payload),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A response packet with a correct length and crc.
+    fn packet(id: u8, status: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::from([0xff, 0xff, id, payload.len() as u8 + 2, status]);
+        packet.extend_from_slice(payload);
+        packet.push(crc(&packet[2..]));
+        packet
+    }
+
+    #[test]
+    fn decodes_a_valid_response() {
+        let response = Response::try_from(packet(3, 0x20, &[0x12, 0x34]).as_slice()).unwrap();
+        assert_eq!(response.id, ID::Single(3));
+        assert_eq!(response.errors.map(DriverErrors::raw), Some(0x20));
+        assert_eq!(response.payload, [0x12, 0x34]);
+
+        let response = Response::try_from(packet(3, 0, &[]).as_slice()).unwrap();
+        assert!(response.errors.is_none());
+        assert!(response.payload.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_bad_header() {
+        let mut bytes = packet(1, 0, &[0x01]);
+        bytes[1] = 0xfe;
+        assert_eq!(
+            Response::try_from(bytes.as_slice()).err(),
+            Some(ResponseError::Header)
+        );
+    }
+
+    #[test]
+    fn rejects_a_length_too_short() {
+        let mut bytes = packet(1, 0, &[]);
+        bytes[3] = 1;
+        assert_eq!(
+            Response::try_from(bytes.as_slice()).err(),
+            Some(ResponseError::LengthTooShort(1))
+        );
+    }
+
+    #[test]
+    fn rejects_a_bad_crc() {
+        let mut bytes = packet(1, 0, &[0x01, 0x02]);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(matches!(
+            Response::try_from(bytes.as_slice()),
+            Err(ResponseError::CrcInvalid { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        assert_eq!(
+            Response::try_from([0xff, 0xff, 0x01].as_slice()).err(),
+            Some(ResponseError::Truncated {
+                expected: 4,
+                received: 3,
+            })
+        );
+
+        let bytes = packet(1, 0, &[0x01, 0x02]);
+        assert_eq!(
+            Response::try_from(&bytes[..bytes.len() - 1]).err(),
+            Some(ResponseError::Truncated {
+                expected: 8,
+                received: 7,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = packet(1, 0, &[0x01, 0x02]);
+        bytes.push(0x00);
+        assert_eq!(
+            Response::try_from(bytes.as_slice()).err(),
+            Some(ResponseError::TrailingBytes {
+                expected: 8,
+                received: 9,
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializing_checks_the_payload_fits() {
+        let fields = |length: usize| {
+            let payload = vec!["0"; length].join(",");
+            format!(r#"{{"id":1,"errors":null,"payload":[{payload}]}}"#)
+        };
+
+        let response: Response = serde_json::from_str(&fields(253)).unwrap();
+        assert_eq!(response.payload.len(), 253);
+        let result = serde_json::from_str::<Response>(&fields(254));
+        assert!(result.unwrap_err().to_string().contains("254 bytes"));
+        assert!(
+            serde_json::from_str::<Response>(r#"{"id":254,"errors":null,"payload":[]}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_ids_no_servo_answers_from() {
+        for id in [0xfd, 0xfe, 0xff] {
+            assert_eq!(
+                Response::try_from(packet(id, 0, &[]).as_slice()).err(),
+                Some(ResponseError::IdInvalid(id))
+            );
+        }
+    }
+}
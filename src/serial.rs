@@ -1,4 +1,5 @@
-use serialport::SerialPort;
+use std::io::{Read, Write};
+
 use thiserror::Error;
 
 use crate::{
@@ -13,18 +14,29 @@ pub enum SerialError {
     IO(#[from] std::io::Error),
     #[error("Response Error: {0}")]
     Response(#[from] response::ResponseError),
+    #[error("Expected a response from {expected:?}, but received one from {received:?}.")]
+    WrongId { expected: ID, received: ID },
+    #[error("Expected a payload of {expected} bytes, but received {received}.")]
+    UnexpectedLength { expected: usize, received: usize },
 }
 
+/// Anything packets can be written to and read back from, like a serial port or a
+/// [`crate::sim::Simulator`].
+pub trait Port: Read + Write {}
+
+impl<T: Read + Write> Port for T {}
+
 pub fn packet_tx_rx(
     transmit: Command,
-    port: &mut Box<dyn SerialPort>,
+    port: &mut impl Port,
 ) -> Result<Option<Response>, SerialError> {
-    let is_broadcast = matches!(transmit.id, ID::Broadcast);
+    let id = transmit.id;
+    let expected_length = transmit.instruction.response_length();
     let built = transmit.build();
     port.write_all(&built)?;
 
     // Device does not respond to broadcast, so do not listen for response.
-    if is_broadcast {
+    if matches!(id, ID::Broadcast) {
         return Ok(None);
     }
 
@@ -35,5 +47,88 @@ pub fn packet_tx_rx(
     port.read_exact(&mut remain)?;
     receive.extend(remain);
 
-    Ok(Some(Response::try_from(receive.as_slice())?))
+    let response = Response::try_from(receive.as_slice())?;
+
+    // A response from another servo, or of the wrong size, means the bus is out of step.
+    if response.id != id {
+        return Err(SerialError::WrongId {
+            expected: id,
+            received: response.id,
+        });
+    }
+    if response.payload.len() != expected_length {
+        return Err(SerialError::UnexpectedLength {
+            expected: expected_length,
+            received: response.payload.len(),
+        });
+    }
+
+    Ok(Some(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::{
+            address::{self, ReadRegion},
+            Instruction,
+        },
+        sim::Simulator,
+    };
+
+    /// A bus with servos 1 and 2, where `stray` was sent without reading its response, as if an
+    /// earlier exchange timed out before the servo answered.
+    fn out_of_step(stray: Command) -> Simulator {
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ID::single(1).unwrap());
+        sim.add_servo(ID::single(2).unwrap());
+        sim.write_all(&stray.build()).unwrap();
+        sim
+    }
+
+    #[test]
+    fn rejects_a_response_from_another_servo() {
+        let (one, two) = (ID::single(1).unwrap(), ID::single(2).unwrap());
+        let mut sim = out_of_step(Command::new(two, Instruction::Ping));
+
+        let result = packet_tx_rx(Command::new(one, Instruction::Ping), &mut sim);
+        assert!(matches!(
+            result,
+            Err(SerialError::WrongId { expected, received }) if expected == one && received == two
+        ));
+    }
+
+    #[test]
+    fn rejects_a_response_of_the_wrong_length() {
+        let id = ID::single(1).unwrap();
+        let byte = ReadRegion::one(address::ID);
+        let mut sim = out_of_step(Command::new(id, Instruction::read(byte)));
+
+        let word = ReadRegion::one(address::PresentPosition);
+        let result = packet_tx_rx(Command::new(id, Instruction::read(word)), &mut sim);
+        assert!(matches!(
+            result,
+            Err(SerialError::UnexpectedLength {
+                expected: 2,
+                received: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn accepts_the_expected_response() {
+        let id = ID::single(1).unwrap();
+        let mut sim = out_of_step(Command::new(ID::single(2).unwrap(), Instruction::Ping));
+        // Skip the stray response.
+        let mut stray = [0; 6];
+        sim.read_exact(&mut stray).unwrap();
+
+        let word = ReadRegion::one(address::PresentPosition);
+        let response = packet_tx_rx(Command::new(id, Instruction::read(word)), &mut sim)
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.id, id);
+        assert_eq!(response.payload, [0x00, 0x08]);
+    }
 }
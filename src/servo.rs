@@ -1,14 +1,19 @@
+use std::{
+    borrow::Cow, collections::BTreeMap, io, ops::RangeInclusive, thread::sleep, time::Duration,
+};
+
 use crate::{
     command::Command,
     hardware::{
-        address::{self, WriteRegion},
-        DriverErrors, Instruction, ID,
+        address::{self, Address, ReadRegion, WriteRegion},
+        decode_signed, encode_signed, sign_bit, DriverErrors, IDError, Instruction, ID,
+        OFFSET_SIGN_BIT, SPEED_SIGN_BIT,
     },
     response::Response,
-    serial::{self, SerialError},
+    serial::{self, Port, SerialError},
+    wait::{WaitConfig, WaitError, Waiter},
 };
-use angle::Angle;
-use serialport::SerialPort;
+use angle::{Angle, Deg};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,6 +24,12 @@ pub enum ServoError {
     Driver(#[from] DriverErrors),
     #[error("A response was expected, but none received.")]
     NoResponse,
+    #[error("Property Error: {0}")]
+    Property(#[from] PropertyError),
+    #[error("Limit Error: {0}")]
+    Limit(#[from] LimitError),
+    #[error("ID Error: {0}")]
+    ID(#[from] IDError),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -47,16 +58,29 @@ impl Assign {
         Self::default()
     }
 
+    /// Move to `position` limited by `speed` and `acceleration`.
+    ///
+    /// `GoalTime` is cleared, both to make memory continuous and so an earlier time does not
+    /// linger. A speed of zero lets the servo move at full speed.
     pub fn new_position_goal(position: Position, speed: Speed, acceleration: Acceleration) -> Self {
-        let mut order = Self::default()
+        Self::default()
             .with(acceleration)
             .with(position)
-            .with(speed);
-
-        // Set to make memory continuous.
-        order.set_word(address::GoalTime, Some(0));
+            .with(GoalTime(0))
+            .with(speed)
+    }
 
-        order
+    /// Move to `position`, arriving after `time`.
+    ///
+    /// The firmware only uses `GoalTime` while `GoalSpeed` is zero, as any non-zero speed takes
+    /// precedence. So the speed is cleared, along with the acceleration, leaving the servo to
+    /// pick the constant speed that covers the distance in `time`.
+    pub fn new_timed_position_goal(position: Position, time: GoalTime) -> Self {
+        Self::default()
+            .with(Acceleration(0))
+            .with(position)
+            .with(time)
+            .with(Speed(0))
     }
 
     pub fn with<T: AssignProperty>(mut self, property: T) -> Self {
@@ -78,8 +102,47 @@ impl Assign {
         self.0[address.index_h() as usize] = h;
     }
 
+    pub fn get_byte<A: address::ByteAddress>(&self, address: A) -> Option<u8> {
+        self.0[address.index() as usize]
+    }
+
+    pub fn get_word<A: address::WordAddress>(&self, address: A) -> Option<u16> {
+        let l = self.0[address.index_l() as usize]?;
+        let h = self.0[address.index_h() as usize]?;
+        Some(u16::from_le_bytes([l, h]))
+    }
+
+    /// Set a register chosen at runtime to a raw value, which must be one its property type
+    /// could have written.
+    pub fn set_register(&mut self, address: &dyn Address, value: u16) -> Result<(), PropertyError> {
+        if !address.access().can_write() {
+            return Err(PropertyError::ReadOnly);
+        }
+        if !valid_register_value(address, value) {
+            return Err(PropertyError::OutOfRange);
+        }
+
+        let index = address.index() as usize;
+        match address.size() {
+            address::Size::Byte => self.0[index] = Some(value as u8),
+            address::Size::Word => {
+                let [l, h] = value.to_le_bytes();
+                self.0[index] = Some(l);
+                self.0[index + 1] = Some(h);
+            }
+        }
+        Ok(())
+    }
+
     fn get_instructions(&self) -> Vec<Instruction> {
-        // Collect the addresses into contiguous instructions
+        self.get_regions()
+            .into_iter()
+            .map(Instruction::write)
+            .collect()
+    }
+
+    fn get_regions(&self) -> Vec<WriteRegion> {
+        // Collect the addresses into contiguous regions
         self.0
             .iter()
             .copied()
@@ -87,18 +150,18 @@ impl Assign {
             .enumerate()
             .fold(
                 (Vec::new(), None::<(usize, Vec<u8>)>),
-                |(mut instructions, collected), (index, value)| match (collected, value) {
+                |(mut regions, collected), (index, value)| match (collected, value) {
                     (Some((start, mut collected)), Some(value)) => {
                         collected.push(value);
-                        (instructions, Some((start, collected)))
+                        (regions, Some((start, collected)))
                     }
-                    (None, Some(value)) => (instructions, Some((index, Vec::from([value])))),
-                    (None, None) => (instructions, None),
+                    (None, Some(value)) => (regions, Some((index, Vec::from([value])))),
+                    (None, None) => (regions, None),
                     (Some((start, collected)), None) => {
                         let region = address::WriteRegion::new(start as u8, collected)
                             .expect("as_memory must store valid memory regions");
-                        instructions.push(Instruction::write(region));
-                        (instructions, None)
+                        regions.push(region);
+                        (regions, None)
                     }
                 },
             )
@@ -116,6 +179,85 @@ fn split_word(word: Option<u16>) -> (Option<u8>, Option<u8>) {
     }
 }
 
+/// An assignment is written as a map of register names to values, like
+/// `{ "GoalPosition": 2048, "GoalSpeed": 500 }`. Reading one back checks every value is valid
+/// for its register.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Assign {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let registers = address::all().filter_map(|address| {
+            let index = address.index() as usize;
+            let value = match address.size() {
+                address::Size::Byte => self.0.get(index).copied()?? as u16,
+                address::Size::Word => {
+                    let (l, h) = (
+                        self.0.get(index).copied()??,
+                        self.0.get(index + 1).copied()??,
+                    );
+                    u16::from_le_bytes([l, h])
+                }
+            };
+            Some((address.name(), value))
+        });
+        serializer.collect_map(registers)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Assign {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let registers = std::collections::BTreeMap::<String, u16>::deserialize(deserializer)?;
+        let mut assign = Assign::new();
+        for (name, value) in registers {
+            let address = address::address_by_name(&name)
+                .filter(|address| address.access().can_write())
+                .ok_or_else(|| D::Error::custom(format!("{name} is not a writeable register")))?;
+            assign
+                .set_register(address.as_ref(), value)
+                .map_err(|_| D::Error::custom(format!("{value} is not valid for {name}")))?;
+        }
+        Ok(assign)
+    }
+}
+
+/// Whether a value could have been assigned to a register through its property type.
+fn valid_register_value(address: &dyn Address, value: u16) -> bool {
+    let byte = u8::try_from(value);
+    match address.index() {
+        index if index == address::ID.index() => byte.is_ok_and(|id| ID::single(id).is_ok()),
+        index if index == address::BaudRate.index() => {
+            byte.is_ok_and(|baud| BaudRate::n(baud).is_some())
+        }
+        index
+            if index == address::MinAngleLimit.index()
+                || index == address::MaxAngleLimit.index()
+                || index == address::GoalPosition.index() =>
+        {
+            Position::new_raw(value).is_ok()
+        }
+        index if index == address::Mode.index() || index == address::Lock.index() => {
+            matches!(value, 0 | 1)
+        }
+        index if index == address::TorqueEnable.index() => matches!(value, 0 | 1 | 128),
+        index if index == address::Acceleration.index() => {
+            byte.is_ok_and(|acceleration| Acceleration::new_raw(acceleration).is_ok())
+        }
+        index if index == address::GoalTime.index() => GoalTime::new_raw(value).is_ok(),
+        // Either a `Speed`, or a signed `WheelSpeed`.
+        index if index == address::GoalSpeed.index() => {
+            value & !(1 << SPEED_SIGN_BIT) <= Speed::MAX
+        }
+        index if index == address::TorqueLimit.index() => TorqueLimit::new_raw(value).is_ok(),
+        index => match (sign_bit(index), address.size()) {
+            (Some(sign_bit), _) => value >> (sign_bit + 1) == 0,
+            (None, address::Size::Byte) => byte.is_ok(),
+            (None, address::Size::Word) => true,
+        },
+    }
+}
+
 pub trait AssignProperty {
     fn apply_to(self, assign: &mut Assign);
 }
@@ -124,14 +266,24 @@ pub trait AssignProperty {
 pub enum PropertyError {
     #[error("The property is out of range.")]
     OutOfRange,
+    #[error("The minimum must be below the maximum.")]
+    MinNotBelowMax,
+    #[error("The register cannot be written.")]
+    ReadOnly,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u16", into = "u16")
+)]
 pub struct Speed(u16);
 
 impl Speed {
     const MIN: u16 = 0;
-    const MAX: u16 = 0xfff;
+    /// The fastest goal speed, in steps per second.
+    pub const MAX: u16 = 0xfff;
 
     pub fn new(value: f32) -> Self {
         let value = (value * (Self::MAX - Self::MIN) as f32) as u16 + Self::MIN;
@@ -141,9 +293,27 @@ impl Speed {
     pub fn new_raw(value: u16) -> Result<Self, PropertyError> {
         (Self::MIN..=Self::MAX)
             .contains(&value)
-            .then(|| Self(value))
+            .then_some(Self(value))
             .ok_or(PropertyError::OutOfRange)
     }
+
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for Speed {
+    type Error = PropertyError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new_raw(value)
+    }
+}
+
+impl From<Speed> for u16 {
+    fn from(value: Speed) -> Self {
+        value.0
+    }
 }
 
 impl AssignProperty for Speed {
@@ -152,12 +322,67 @@ impl AssignProperty for Speed {
     }
 }
 
+/// A signed goal speed for wheel mode, in steps per second. Negative speeds turn the other way.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "i16", into = "i16")
+)]
+pub struct WheelSpeed(i16);
+
+impl WheelSpeed {
+    pub(crate) const MAX: i16 = Speed::MAX as i16;
+
+    /// A fraction of the maximum speed, from -1 to 1.
+    pub fn new(value: f32) -> Self {
+        Self((value.clamp(-1.0, 1.0) * Self::MAX as f32) as i16)
+    }
+
+    pub fn new_raw(value: i16) -> Result<Self, PropertyError> {
+        (-Self::MAX..=Self::MAX)
+            .contains(&value)
+            .then_some(Self(value))
+            .ok_or(PropertyError::OutOfRange)
+    }
+
+    pub fn value(self) -> i16 {
+        self.0
+    }
+}
+
+impl TryFrom<i16> for WheelSpeed {
+    type Error = PropertyError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        Self::new_raw(value)
+    }
+}
+
+impl From<WheelSpeed> for i16 {
+    fn from(value: WheelSpeed) -> Self {
+        value.0
+    }
+}
+
+impl AssignProperty for WheelSpeed {
+    fn apply_to(self, assign: &mut Assign) {
+        let value = encode_signed(self.0 as i32, SPEED_SIGN_BIT);
+        assign.set_word(address::GoalSpeed, Some(value));
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u16", into = "u16")
+)]
 pub struct Position(u16);
 
 impl Position {
     const MIN: u16 = 0;
-    const MAX: u16 = 0xfff;
+    pub(crate) const MAX: u16 = 0xfff;
 
     pub fn new<T: Angle<f32>>(position: T) -> Self {
         let position = ((position.to_deg().as_value() * 4096.0 / 360.0).round() as u16)
@@ -168,9 +393,36 @@ impl Position {
     pub fn new_raw(value: u16) -> Result<Self, PropertyError> {
         (Self::MIN..=Self::MAX)
             .contains(&value)
-            .then(|| Self(value))
+            .then_some(Self(value))
             .ok_or(PropertyError::OutOfRange)
     }
+
+    /// Round a fractional step count to the nearest valid position.
+    pub(crate) fn from_steps(steps: f32) -> Self {
+        Self((steps.round().max(0.0) as u16).clamp(Self::MIN, Self::MAX))
+    }
+
+    pub fn value(self) -> u16 {
+        self.0
+    }
+
+    pub fn to_deg(self) -> Deg<f32> {
+        Deg(self.0 as f32 * 360.0 / 4096.0)
+    }
+}
+
+impl TryFrom<u16> for Position {
+    type Error = PropertyError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new_raw(value)
+    }
+}
+
+impl From<Position> for u16 {
+    fn from(value: Position) -> Self {
+        value.0
+    }
 }
 
 impl AssignProperty for Position {
@@ -180,6 +432,11 @@ impl AssignProperty for Position {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
 pub struct Acceleration(u8);
 
 impl Acceleration {
@@ -194,9 +451,27 @@ impl Acceleration {
     pub fn new_raw(value: u8) -> Result<Self, PropertyError> {
         (Self::MIN..=Self::MAX)
             .contains(&value)
-            .then(|| Self(value))
+            .then_some(Self(value))
             .ok_or(PropertyError::OutOfRange)
     }
+
+    pub fn value(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Acceleration {
+    type Error = PropertyError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::new_raw(value)
+    }
+}
+
+impl From<Acceleration> for u8 {
+    fn from(value: Acceleration) -> Self {
+        value.0
+    }
 }
 
 impl AssignProperty for Acceleration {
@@ -205,8 +480,88 @@ impl AssignProperty for Acceleration {
     }
 }
 
-#[repr(u8)]
+/// How long a move should take, in milliseconds.
 #[derive(Debug, Clone, Copy)]
+pub struct GoalTime(u16);
+
+impl GoalTime {
+    const MIN: u16 = 0;
+    const MAX: u16 = 0x7fff;
+
+    pub fn new(time: Duration) -> Result<Self, PropertyError> {
+        let value = time
+            .as_millis()
+            .try_into()
+            .map_err(|_| PropertyError::OutOfRange)?;
+        Self::new_raw(value)
+    }
+
+    /// Like [`GoalTime::new`], but times longer than the servo supports are shortened to the
+    /// longest it does.
+    pub fn saturating(time: Duration) -> Self {
+        Self(time.as_millis().min(Self::MAX.into()) as u16)
+    }
+
+    pub fn new_raw(value: u16) -> Result<Self, PropertyError> {
+        (Self::MIN..=Self::MAX)
+            .contains(&value)
+            .then_some(Self(value))
+            .ok_or(PropertyError::OutOfRange)
+    }
+
+    pub fn value(self) -> u16 {
+        self.0
+    }
+
+    pub fn to_duration(self) -> Duration {
+        Duration::from_millis(self.0.into())
+    }
+}
+
+impl AssignProperty for GoalTime {
+    fn apply_to(self, assign: &mut Assign) {
+        assign.set_word(address::GoalTime, Some(self.0));
+    }
+}
+
+/// The maximum torque the servo may output, in 0.1% of its rated torque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TorqueLimit(u16);
+
+impl TorqueLimit {
+    const MIN: u16 = 0;
+    const MAX: u16 = 1000;
+
+    pub fn new(fraction: f32) -> Self {
+        let value = (fraction * (Self::MAX - Self::MIN) as f32) as u16 + Self::MIN;
+        Self(value.clamp(Self::MIN, Self::MAX))
+    }
+
+    pub fn new_raw(value: u16) -> Result<Self, PropertyError> {
+        (Self::MIN..=Self::MAX)
+            .contains(&value)
+            .then_some(Self(value))
+            .ok_or(PropertyError::OutOfRange)
+    }
+
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl AssignProperty for TorqueLimit {
+    fn apply_to(self, assign: &mut Assign) {
+        assign.set_word(address::TorqueLimit, Some(self.0));
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Mode {
     Position = 0,
     Wheel = 1,
@@ -218,65 +573,789 @@ impl AssignProperty for Mode {
     }
 }
 
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+pub enum Torque {
+    Disabled = 0,
+    Enabled = 1,
+    /// Make the present position the midpoint, 2048, by rewriting the offset.
+    Calibrate = 128,
+}
+
+impl AssignProperty for Torque {
+    fn apply_to(self, assign: &mut Assign) {
+        assign.set_byte(address::TorqueEnable, Some(self as u8));
+    }
+}
+
+/// The bus speed, as stored in the `BaudRate` register.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, enumn::N)]
+pub enum BaudRate {
+    B1000000 = 0,
+    B500000 = 1,
+    B250000 = 2,
+    B128000 = 3,
+    B115200 = 4,
+    B76800 = 5,
+    B57600 = 6,
+    B38400 = 7,
+}
+
+impl BaudRate {
+    const VALUES: [Self; 8] = [
+        Self::B1000000,
+        Self::B500000,
+        Self::B250000,
+        Self::B128000,
+        Self::B115200,
+        Self::B76800,
+        Self::B57600,
+        Self::B38400,
+    ];
+
+    pub fn bits_per_second(self) -> u32 {
+        match self {
+            Self::B1000000 => 1_000_000,
+            Self::B500000 => 500_000,
+            Self::B250000 => 250_000,
+            Self::B128000 => 128_000,
+            Self::B115200 => 115_200,
+            Self::B76800 => 76_800,
+            Self::B57600 => 57_600,
+            Self::B38400 => 38_400,
+        }
+    }
+
+    pub fn from_bits_per_second(value: u32) -> Option<Self> {
+        Self::VALUES
+            .into_iter()
+            .find(|baud| baud.bits_per_second() == value)
+    }
+}
+
+impl AssignProperty for BaudRate {
+    fn apply_to(self, assign: &mut Assign) {
+        assign.set_byte(address::BaudRate, Some(self as u8));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum LimitPolicy {
+    /// Move goals outside the limits to the nearest limit.
+    Clamp,
+    /// Refuse to send goals outside the limits.
+    Reject,
+}
+
+#[derive(Debug, Error, Clone, Copy)]
+pub enum LimitError {
+    #[error("Goal {goal:?} is below the minimum limit {min:?}.")]
+    BelowMin { goal: Position, min: Position },
+    #[error("Goal {goal:?} is above the maximum limit {max:?}.")]
+    AboveMax { goal: Position, max: Position },
+}
+
+/// Application limits on goal positions, checked before a goal is sent.
+///
+/// Unlike `MinAngleLimit` and `MaxAngleLimit`, these live only on the host, so they can differ
+/// between applications without rewriting the servo's EEPROM.
+#[derive(Debug, Clone, Copy)]
+pub struct SoftLimits {
+    min: Position,
+    max: Position,
+    policy: LimitPolicy,
+}
+
+impl SoftLimits {
+    /// `min` must be below `max`.
+    pub fn new(min: Position, max: Position, policy: LimitPolicy) -> Result<Self, PropertyError> {
+        (min.0 < max.0)
+            .then_some(Self { min, max, policy })
+            .ok_or(PropertyError::MinNotBelowMax)
+    }
+
+    /// Check the limits lie within `(min, max)`, such as the range a servo reports through
+    /// [`Servo::read_position_range`].
+    pub fn within(self, (min, max): (Position, Position)) -> Result<Self, PropertyError> {
+        (min.0 <= self.min.0 && self.max.0 <= max.0)
+            .then_some(self)
+            .ok_or(PropertyError::OutOfRange)
+    }
+
+    pub fn min(&self) -> Position {
+        self.min
+    }
+
+    pub fn max(&self) -> Position {
+        self.max
+    }
+
+    pub fn policy(&self) -> LimitPolicy {
+        self.policy
+    }
+
+    /// The goal to send in place of `goal`.
+    pub fn apply(&self, goal: Position) -> Result<Position, LimitError> {
+        let error = if goal.0 < self.min.0 {
+            LimitError::BelowMin {
+                goal,
+                min: self.min,
+            }
+        } else if goal.0 > self.max.0 {
+            LimitError::AboveMax {
+                goal,
+                max: self.max,
+            }
+        } else {
+            return Ok(goal);
+        };
+
+        match self.policy {
+            LimitPolicy::Clamp => Ok(Position(goal.0.clamp(self.min.0, self.max.0))),
+            LimitPolicy::Reject => Err(error),
+        }
+    }
+}
+
+/// What a servo handle does when a response carries a non-zero status byte.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum StatusPolicy {
+    /// Only report the status through [`Response::errors`].
+    #[default]
+    Ignore,
+    /// Fail with [`ServoError::Driver`] when a known error is set.
+    FailOnKnown,
+    /// Fail with [`ServoError::Driver`] when any bit is set.
+    FailOnAny,
+}
+
+impl StatusPolicy {
+    /// Whether a response carrying `errors` fails under this policy.
+    pub fn fails(self, errors: DriverErrors) -> bool {
+        match self {
+            Self::Ignore => false,
+            Self::FailOnKnown => errors.has_known(),
+            Self::FailOnAny => errors.raw() != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Servo {
     id: ID,
+    limits: Option<SoftLimits>,
+    status_policy: StatusPolicy,
 }
 
 impl Servo {
     pub fn new(id: ID) -> Self {
-        Self { id }
+        Self {
+            id,
+            limits: None,
+            status_policy: StatusPolicy::default(),
+        }
+    }
+
+    pub fn with_status_policy(mut self, policy: StatusPolicy) -> Self {
+        self.status_policy = policy;
+        self
+    }
+
+    pub fn status_policy(&self) -> StatusPolicy {
+        self.status_policy
+    }
+
+    /// Check every goal position written through this servo against `limits`.
+    pub fn with_limits(mut self, limits: SoftLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    pub fn id(&self) -> ID {
+        self.id
+    }
+
+    pub fn limits(&self) -> Option<SoftLimits> {
+        self.limits
+    }
+
+    /// The goal that would be sent in place of `goal`, after applying any soft limits.
+    pub fn limit(&self, goal: Position) -> Result<Position, LimitError> {
+        match self.limits {
+            Some(limits) => limits.apply(goal),
+            None => Ok(goal),
+        }
     }
 
-    pub fn ping(&self, port: &mut Box<dyn SerialPort>) -> Result<Response, ServoError> {
+    pub fn ping(&self, port: &mut impl Port) -> Result<Response, ServoError> {
         let ping = Command::new(self.id, Instruction::Ping);
 
-        expect_response(serial::packet_tx_rx(ping, port))
+        expect_response(self.transact(ping, port))
     }
 
-    pub fn write_id(
-        &mut self,
-        new_id: ID,
-        port: &mut Box<dyn SerialPort>,
-    ) -> Result<Response, ServoError> {
+    pub fn write_id(&mut self, new_id: ID, port: &mut impl Port) -> Result<Response, ServoError> {
         let region = WriteRegion::one(address::ID, new_id.value());
         let instruction = Instruction::write(region);
         let command = Command::new(self.id, instruction);
 
         self.write_eeprom_lock(false, port)?;
-        let response = expect_response(serial::packet_tx_rx(command, port))?;
+        let response = expect_response(self.transact(command, port))?;
         self.id = new_id;
         self.write_eeprom_lock(true, port)?;
 
         Ok(response)
     }
 
-    pub fn write(&self, assign: &Assign, port: &mut Box<dyn SerialPort>) -> Result<(), ServoError> {
+    /// Write every assigned value. A goal position is first checked against any soft limits.
+    pub fn write(&self, assign: &Assign, port: &mut impl Port) -> Result<(), ServoError> {
+        let assign = self.limit_assign(assign)?;
         for instruction in assign.get_instructions() {
             let command = Command::new(self.id, instruction);
-            serial::packet_tx_rx(command, port)?;
+            self.transact(command, port)?;
         }
         Ok(())
     }
 
-    fn write_eeprom_lock(
+    pub fn read_byte<A: address::ReadableAddress + address::ByteAddress>(
+        &self,
+        address: A,
+        port: &mut impl Port,
+    ) -> Result<u8, ServoError> {
+        let [value] = self.read_region(ReadRegion::one(address), port)?;
+        Ok(value)
+    }
+
+    pub fn read_word<A: address::ReadableAddress + address::WordAddress>(
+        &self,
+        address: A,
+        port: &mut impl Port,
+    ) -> Result<u16, ServoError> {
+        let bytes = self.read_region(ReadRegion::one(address), port)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    pub fn read_position(&self, port: &mut impl Port) -> Result<Position, ServoError> {
+        let value = self.read_word(address::PresentPosition, port)?;
+        Ok(Position::new_raw(value)?)
+    }
+
+    /// Read the present speed, signed, in steps per second.
+    pub fn read_speed(&self, port: &mut impl Port) -> Result<i32, ServoError> {
+        let value = self.read_word(address::PresentSpeed, port)?;
+        Ok(decode_signed(value, SPEED_SIGN_BIT))
+    }
+
+    /// Read the EEPROM angle limits, as `(min, max)`.
+    pub fn read_angle_limits(
+        &self,
+        port: &mut impl Port,
+    ) -> Result<(Deg<f32>, Deg<f32>), ServoError> {
+        let (min, max) = self.read_angle_limit_steps(port)?;
+        Ok((steps_to_deg(min.into()), steps_to_deg(max.into())))
+    }
+
+    /// Read the EEPROM angle limits as the positions the servo can be sent to, as `(min, max)`.
+    pub fn read_position_range(
+        &self,
+        port: &mut impl Port,
+    ) -> Result<(Position, Position), ServoError> {
+        let (min, max) = self.read_angle_limit_steps(port)?;
+        Ok((Position::new_raw(min)?, Position::new_raw(max)?))
+    }
+
+    fn read_angle_limit_steps(&self, port: &mut impl Port) -> Result<(u16, u16), ServoError> {
+        let region = ReadRegion::new(address::MinAngleLimit.index(), 4)
+            .expect("Angle limits must be readable.");
+        let [min_l, min_h, max_l, max_h] = self.read_region(region, port)?;
+
+        Ok((
+            u16::from_le_bytes([min_l, min_h]),
+            u16::from_le_bytes([max_l, max_h]),
+        ))
+    }
+
+    /// Write the EEPROM angle limits. Both must be within one turn, with `min` below `max`.
+    pub fn write_angle_limits<T: Angle<f32>>(
+        &self,
+        min: T,
+        max: T,
+        port: &mut impl Port,
+    ) -> Result<(), ServoError> {
+        let min = deg_to_steps(min, 0..=Position::MAX as i32)?;
+        let max = deg_to_steps(max, 0..=Position::MAX as i32)?;
+        if min >= max {
+            return Err(PropertyError::MinNotBelowMax.into());
+        }
+
+        let mut assign = Assign::new();
+        assign.set_word(address::MinAngleLimit, Some(min as u16));
+        assign.set_word(address::MaxAngleLimit, Some(max as u16));
+        self.write_eeprom(&assign, port)
+    }
+
+    pub fn read_baud_rate(&self, port: &mut impl Port) -> Result<BaudRate, ServoError> {
+        let value = self.read_byte(address::BaudRate, port)?;
+        BaudRate::n(value).ok_or(ServoError::Property(PropertyError::OutOfRange))
+    }
+
+    /// Write the EEPROM baud rate. The servo answers at the old rate, then only listens at the
+    /// new one, so `switch` moves the port to the new rate before the EEPROM is locked again.
+    pub fn write_baud_rate<P: Port>(
+        &self,
+        baud: BaudRate,
+        port: &mut P,
+        switch: impl FnOnce(&mut P, BaudRate) -> io::Result<()>,
+    ) -> Result<(), ServoError> {
+        self.write_eeprom_lock(false, port)?;
+        if let Err(e) = self.write(&Assign::new().with(baud), port) {
+            self.write_eeprom_lock(true, port)?;
+            return Err(e);
+        }
+
+        switch(port, baud).map_err(SerialError::from)?;
+        self.write_eeprom_lock(true, port)
+    }
+
+    /// Read the EEPROM position offset.
+    pub fn read_offset(&self, port: &mut impl Port) -> Result<Deg<f32>, ServoError> {
+        Ok(steps_to_deg(self.read_offset_steps(port)?))
+    }
+
+    /// Write the EEPROM position offset, which must be under half a turn either way.
+    pub fn write_offset<T: Angle<f32>>(
+        &self,
+        offset: T,
+        port: &mut impl Port,
+    ) -> Result<(), ServoError> {
+        let limit = (1 << OFFSET_SIGN_BIT) - 1;
+        let offset = deg_to_steps(offset, -limit..=limit)?;
+        self.write_offset_steps(offset, port)
+    }
+
+    pub(crate) fn read_offset_steps(&self, port: &mut impl Port) -> Result<i32, ServoError> {
+        let value = self.read_word(address::Ofs, port)?;
+        Ok(decode_signed(value, OFFSET_SIGN_BIT))
+    }
+
+    pub(crate) fn write_offset_steps(
+        &self,
+        offset: i32,
+        port: &mut impl Port,
+    ) -> Result<(), ServoError> {
+        let mut assign = Assign::new();
+        assign.set_word(address::Ofs, Some(encode_signed(offset, OFFSET_SIGN_BIT)));
+        self.write_eeprom(&assign, port)
+    }
+
+    /// Read the EEPROM dead bands, as `(clockwise, counter_clockwise)`.
+    pub fn read_dead_bands(
+        &self,
+        port: &mut impl Port,
+    ) -> Result<(Deg<f32>, Deg<f32>), ServoError> {
+        let region =
+            ReadRegion::new(address::CwDead.index(), 2).expect("Dead bands must be readable.");
+        let [cw, ccw] = self.read_region(region, port)?;
+        Ok((steps_to_deg(cw.into()), steps_to_deg(ccw.into())))
+    }
+
+    /// Write the EEPROM dead bands, each up to 255 steps.
+    pub fn write_dead_bands<T: Angle<f32>>(
+        &self,
+        cw: T,
+        ccw: T,
+        port: &mut impl Port,
+    ) -> Result<(), ServoError> {
+        let cw = deg_to_steps(cw, 0..=u8::MAX as i32)?;
+        let ccw = deg_to_steps(ccw, 0..=u8::MAX as i32)?;
+
+        let mut assign = Assign::new();
+        assign.set_byte(address::CwDead, Some(cw as u8));
+        assign.set_byte(address::CcwDead, Some(ccw as u8));
+        self.write_eeprom(&assign, port)
+    }
+
+    /// Unlock the EEPROM, write, then lock it again so the values are kept after power off.
+    pub(crate) fn write_eeprom(
         &self,
-        locked: bool,
-        port: &mut Box<dyn SerialPort>,
+        assign: &Assign,
+        port: &mut impl Port,
     ) -> Result<(), ServoError> {
+        self.write_eeprom_lock(false, port)?;
+        let result = self.write(assign, port);
+        self.write_eeprom_lock(true, port)?;
+
+        result
+    }
+
+    /// Write raw bytes, without checking them against any soft limits.
+    pub fn write_region(
+        &self,
+        region: WriteRegion,
+        port: &mut impl Port,
+    ) -> Result<(), ServoError> {
+        let command = Command::new(self.id, Instruction::write(region));
+        self.transact(command, port)?;
+        Ok(())
+    }
+
+    pub fn read(&self, region: ReadRegion, port: &mut impl Port) -> Result<Response, ServoError> {
+        let command = Command::new(self.id, Instruction::read(region));
+        expect_response(self.transact(command, port))
+    }
+
+    /// Block until the servo has stopped within `config.tolerance` of `goal`.
+    ///
+    /// See [`Waiter`] to check without blocking.
+    pub fn wait_until_stopped(
+        &self,
+        goal: Position,
+        config: &WaitConfig,
+        port: &mut impl Port,
+    ) -> Result<Position, WaitError> {
+        let mut waiter = Waiter::new(self.clone(), goal, config.clone());
+        loop {
+            if let Some(position) = waiter.check(port)? {
+                return Ok(position);
+            }
+            sleep(config.poll_period);
+        }
+    }
+
+    fn read_region<const N: usize>(
+        &self,
+        region: ReadRegion,
+        port: &mut impl Port,
+    ) -> Result<[u8; N], ServoError> {
+        let response = self.read(region, port)?;
+        payload(&response)
+    }
+
+    fn limit_assign<'a>(&self, assign: &'a Assign) -> Result<Cow<'a, Assign>, LimitError> {
+        let Some(goal) = assign.get_word(address::GoalPosition) else {
+            return Ok(Cow::Borrowed(assign));
+        };
+
+        let limited = self.limit(Position(goal))?;
+        if limited.0 == goal {
+            Ok(Cow::Borrowed(assign))
+        } else {
+            Ok(Cow::Owned(assign.clone().with(limited)))
+        }
+    }
+
+    /// Lock or unlock the EEPROM. Values written while unlocked are kept after power off.
+    pub fn write_eeprom_lock(&self, locked: bool, port: &mut impl Port) -> Result<(), ServoError> {
         let locked = if locked { 1 } else { 0 };
         let region = WriteRegion::one(address::Lock, locked);
         let instruction = Instruction::write(region);
         let command = Command::new(self.id, instruction);
-        serial::packet_tx_rx(command, port)?;
+        self.transact(command, port)?;
 
         Ok(())
     }
+
+    /// Send a command, applying the status policy to any response.
+    fn transact(
+        &self,
+        command: Command,
+        port: &mut impl Port,
+    ) -> Result<Option<Response>, ServoError> {
+        let response = serial::packet_tx_rx(command, port)?;
+
+        match response.as_ref().and_then(|response| response.errors) {
+            Some(errors) if self.status_policy.fails(errors) => Err(ServoError::Driver(errors)),
+            _ => Ok(response),
+        }
+    }
+}
+
+/// Steps in one turn of a servo, after which `PresentPosition` rolls over.
+pub(crate) const STEPS_PER_TURN: u16 = 4096;
+
+fn steps_to_deg(steps: i32) -> Deg<f32> {
+    Deg(steps as f32 * 360.0 / 4096.0)
+}
+
+/// Convert an angle to whole steps, which must be within `range`.
+fn deg_to_steps<T: Angle<f32>>(angle: T, range: RangeInclusive<i32>) -> Result<i32, PropertyError> {
+    let steps = (angle.to_deg().as_value() * 4096.0 / 360.0).round() as i32;
+    range
+        .contains(&steps)
+        .then_some(steps)
+        .ok_or(PropertyError::OutOfRange)
+}
+
+/// Write each servo's assignment using as few packets as possible. Goal positions are checked
+/// against each servo's soft limits before anything is sent.
+///
+/// Regions that share a start address and length across servos are combined into one sync
+/// write, so every servo receives its values at the same moment. Every servo must have a single
+/// id, since a sync write addresses each servo by its own id.
+pub fn sync_write(writes: &[(&Servo, &Assign)], port: &mut impl Port) -> Result<(), ServoError> {
+    let mut groups = BTreeMap::<_, Vec<_>>::new();
+    for (servo, assign) in writes {
+        if servo.id == ID::Broadcast {
+            return Err(IDError::MustNotBroadcast.into());
+        }
+        for region in servo.limit_assign(assign)?.get_regions() {
+            groups
+                .entry((region.start, region.data.len()))
+                .or_default()
+                .push((servo.id, region.data));
+        }
+    }
+
+    for ((start, length), writes) in groups {
+        // Split groups too large for one packet's length field.
+        let per_packet = (u8::MAX as usize - 4) / (length + 1);
+        for chunk in writes.chunks(per_packet) {
+            let region = address::SyncWriteRegion::new(start, chunk.to_vec())
+                .expect("Assign regions to single servos must be valid sync write regions.");
+            let command = Command::new(ID::broadcast(), Instruction::sync_write(region));
+            serial::packet_tx_rx(command, port)?;
+        }
+    }
+
+    Ok(())
 }
 
-fn expect_response(value: Result<Option<Response>, SerialError>) -> Result<Response, ServoError> {
+/// The payload of a response, which must be exactly `N` bytes long.
+pub(crate) fn payload<const N: usize>(response: &Response) -> Result<[u8; N], ServoError> {
+    response.payload.as_slice().try_into().map_err(|_| {
+        ServoError::Serial(SerialError::UnexpectedLength {
+            expected: N,
+            received: response.payload.len(),
+        })
+    })
+}
+
+fn expect_response(value: Result<Option<Response>, ServoError>) -> Result<Response, ServoError> {
     match value {
-        Err(e) => Err(e.into()),
+        Err(e) => Err(e),
         Ok(None) => Err(ServoError::NoResponse),
         Ok(Some(x)) => Ok(x),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulator;
+
+    #[test]
+    fn sync_write_rejects_broadcast() {
+        let mut sim = Simulator::with_manual_clock();
+        let single = Servo::new(ID::single(1).unwrap());
+        let broadcast = Servo::new(ID::broadcast());
+        let assign = Assign::new().with(Speed::new_raw(100).unwrap());
+
+        let result = sync_write(&[(&single, &assign), (&broadcast, &assign)], &mut sim);
+        assert!(matches!(
+            result,
+            Err(ServoError::ID(IDError::MustNotBroadcast))
+        ));
+    }
+
+    #[test]
+    fn set_register_checks_the_value() {
+        let mut assign = Assign::new();
+        assert!(assign.set_register(&address::GoalPosition, 3000).is_ok());
+        assert!(assign.set_register(&address::Ofs, 0x800 | 100).is_ok());
+        assert_eq!(assign.get_word(address::GoalPosition), Some(3000));
+
+        assert!(matches!(
+            assign.set_register(&address::GoalPosition, 60000),
+            Err(PropertyError::OutOfRange)
+        ));
+        assert!(matches!(
+            assign.set_register(&address::Ofs, 5000),
+            Err(PropertyError::OutOfRange)
+        ));
+        assert!(matches!(
+            assign.set_register(&address::PresentPosition, 0),
+            Err(PropertyError::ReadOnly)
+        ));
+        assert_eq!(assign.get_word(address::Ofs), Some(0x800 | 100));
+    }
+
+    #[test]
+    fn baud_rate_locks_at_the_new_rate() {
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id);
+
+        let mut switched = None;
+        Servo::new(id)
+            .write_baud_rate(BaudRate::B115200, &mut sim, |sim, baud| {
+                // The EEPROM is still unlocked when the port switches.
+                assert_eq!(sim.servo(id).unwrap().byte(address::Lock), 0);
+                switched = Some(baud);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(switched, Some(BaudRate::B115200));
+        let servo = sim.servo(id).unwrap();
+        assert_eq!(servo.byte(address::BaudRate), BaudRate::B115200 as u8);
+        assert_eq!(servo.byte(address::Lock), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    fn round_trip(assign: &Assign) -> Assign {
+        let json = serde_json::to_string(assign).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn assign_round_trips() {
+        let assigns = [
+            Assign::new_position_goal(
+                Position::new_raw(3000).unwrap(),
+                Speed::new_raw(Speed::MAX).unwrap(),
+                Acceleration::new_raw(50).unwrap(),
+            ),
+            Assign::new()
+                .with(Mode::Wheel)
+                .with(WheelSpeed::new_raw(-100).unwrap()),
+            Assign::new().with(WheelSpeed::new_raw(-WheelSpeed::MAX).unwrap()),
+            Assign::new_timed_position_goal(
+                Position::new_raw(0).unwrap(),
+                GoalTime::new_raw(1500).unwrap(),
+            ),
+        ];
+
+        for assign in &assigns {
+            assert_eq!(round_trip(assign).0, assign.0);
+        }
+
+        let json = serde_json::to_string(&assigns[1]).unwrap();
+        assert_eq!(json, r#"{"Mode":1,"GoalSpeed":32868}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn assign_rejects_values_no_property_writes() {
+        let invalid = [
+            r#"{"GoalSpeed":4096}"#,
+            r#"{"GoalSpeed":36864}"#,
+            r#"{"GoalPosition":4096}"#,
+            r#"{"Ofs":4096}"#,
+            r#"{"Mode":2}"#,
+            r#"{"Acceleration":255}"#,
+            r#"{"PresentPosition":0}"#,
+        ];
+        for json in invalid {
+            assert!(serde_json::from_str::<Assign>(json).is_err(), "{json}");
+        }
+
+        let assign: Assign = serde_json::from_str(r#"{"Ofs":2148}"#).unwrap();
+        assert_eq!(assign.0[address::Ofs.index() as usize], Some(0x64));
+    }
+
+    #[test]
+    fn soft_limits_need_min_below_max() {
+        let position = |steps| Position::new_raw(steps).unwrap();
+        let new = |min, max| SoftLimits::new(position(min), position(max), LimitPolicy::Clamp);
+
+        assert!(new(1000, 3000).is_ok());
+        assert!(matches!(
+            new(2000, 2000),
+            Err(PropertyError::MinNotBelowMax)
+        ));
+        assert!(matches!(
+            new(3000, 1000),
+            Err(PropertyError::MinNotBelowMax)
+        ));
+    }
+
+    #[test]
+    fn soft_limits_within_servo_range() {
+        let id = ID::single(1).unwrap();
+        let servo = Servo::new(id);
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id);
+        servo
+            .write_angle_limits(Deg(90.0), Deg(270.0), &mut sim)
+            .unwrap();
+
+        let range = servo.read_position_range(&mut sim).unwrap();
+        assert_eq!((range.0.value(), range.1.value()), (1024, 3072));
+
+        let position = |steps| Position::new_raw(steps).unwrap();
+        let limits =
+            |min, max| SoftLimits::new(position(min), position(max), LimitPolicy::Reject).unwrap();
+        assert!(limits(1024, 3072).within(range).is_ok());
+        assert!(limits(2000, 2500).within(range).is_ok());
+        assert!(matches!(
+            limits(0, 2500).within(range),
+            Err(PropertyError::OutOfRange)
+        ));
+        assert!(matches!(
+            limits(2000, 4095).within(range),
+            Err(PropertyError::OutOfRange)
+        ));
+    }
+
+    #[test]
+    fn long_gaps_saturate_the_goal_time() {
+        assert_eq!(
+            GoalTime::saturating(Duration::from_millis(250)).value(),
+            250
+        );
+        assert_eq!(
+            GoalTime::saturating(Duration::from_secs(40)).value(),
+            0x7fff
+        );
+    }
+
+    #[test]
+    fn timed_goals_arrive_on_time() {
+        let id = ID::single(1).unwrap();
+        let servo = Servo::new(id);
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id).set_position(2048.0);
+
+        let goal = Assign::new_timed_position_goal(
+            Position::new_raw(3048).unwrap(),
+            GoalTime::new(Duration::from_secs(1)).unwrap(),
+        );
+        servo.write(&goal, &mut sim).unwrap();
+
+        sim.step(Duration::from_millis(500));
+        let halfway = sim.servo(id).unwrap().position();
+        assert!((2498.0..=2598.0).contains(&halfway), "At {halfway}");
+
+        sim.step(Duration::from_millis(600));
+        assert_eq!(sim.servo(id).unwrap().position(), 3048.0);
+    }
+
+    #[test]
+    fn speed_takes_precedence_over_goal_time() {
+        let id = ID::single(1).unwrap();
+        let servo = Servo::new(id);
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id).set_position(2048.0);
+
+        let goal = Assign::new_position_goal(
+            Position::new_raw(3048).unwrap(),
+            Speed::new_raw(2000).unwrap(),
+            Acceleration::new_raw(0).unwrap(),
+        )
+        .with(GoalTime::new(Duration::from_secs(1)).unwrap());
+        servo.write(&goal, &mut sim).unwrap();
+
+        sim.step(Duration::from_millis(250));
+        let quarter = sim.servo(id).unwrap().position();
+        assert!((2498.0..=2598.0).contains(&quarter), "At {quarter}");
+
+        sim.step(Duration::from_millis(300));
+        assert_eq!(sim.servo(id).unwrap().position(), 3048.0);
+    }
+}
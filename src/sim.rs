@@ -0,0 +1,416 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    crc,
+    hardware::{
+        address::{self, Address, WordAddress},
+        decode_signed, encode_signed, ID, LOAD_SIGN_BIT, OFFSET_SIGN_BIT, SPEED_SIGN_BIT,
+    },
+};
+
+const MEMORY_SIZE: usize = 128;
+
+/// Roughly the no-load speed of an ST3020, in steps per second.
+const MAX_SPEED: f32 = 3400.0;
+
+/// A servo living inside a [`Simulator`].
+///
+/// Positions, speeds and accelerations use the same units as the real registers: steps,
+/// steps per second and 100 steps per second squared.
+#[derive(Debug, Clone)]
+pub struct SimulatedServo {
+    memory: [u8; MEMORY_SIZE],
+    position: f32,
+    velocity: f32,
+    timed_speed: Option<f32>,
+    load: i32,
+    errors: u8,
+}
+
+impl SimulatedServo {
+    fn new(id: u8) -> Self {
+        let mut servo = Self {
+            memory: [0; MEMORY_SIZE],
+            position: 2048.0,
+            velocity: 0.0,
+            timed_speed: None,
+            load: 0,
+            errors: 0,
+        };
+
+        servo.set_word(address::Model, 0x0c09);
+        servo.set_byte(address::ID, id);
+        servo.set_word(address::MaxAngleLimit, 4095);
+        servo.set_byte(address::TorqueEnable, 1);
+        servo.set_word(address::TorqueLimit, 1000);
+        servo.set_word(address::GoalPosition, 2048);
+        servo.set_byte(address::Lock, 1);
+        servo.set_byte(address::PresentVoltage, 120);
+        servo.set_byte(address::PresentTemperature, 25);
+        servo.update(0.0);
+
+        servo
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn byte<A: address::ByteAddress>(&self, address: A) -> u8 {
+        self.memory[address.index() as usize]
+    }
+
+    pub fn word<A: address::WordAddress>(&self, address: A) -> u16 {
+        u16::from_le_bytes([
+            self.memory[address.index_l() as usize],
+            self.memory[address.index_h() as usize],
+        ])
+    }
+
+    /// The exact shaft position in steps, before the offset is applied and the result rounded
+    /// into `PresentPosition`.
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Move the output shaft, as if turned by hand.
+    pub fn set_position(&mut self, steps: f32) {
+        self.position = steps;
+        self.update(0.0);
+    }
+
+    /// Signed load in 0.1% steps of the maximum.
+    pub fn set_load(&mut self, load: i32) {
+        self.load = load;
+        self.update(0.0);
+    }
+
+    /// Voltage in 0.1V steps.
+    pub fn set_voltage(&mut self, voltage: u8) {
+        self.set_byte(address::PresentVoltage, voltage);
+    }
+
+    /// Temperature in degrees celsius.
+    pub fn set_temperature(&mut self, temperature: u8) {
+        self.set_byte(address::PresentTemperature, temperature);
+    }
+
+    pub fn set_current(&mut self, current: u16) {
+        self.set_word(address::PresentCurrent, current);
+    }
+
+    /// The status byte sent with every response.
+    pub fn set_errors(&mut self, errors: u8) {
+        self.errors = errors;
+    }
+
+    fn set_byte<A: address::ByteAddress>(&mut self, address: A, value: u8) {
+        self.memory[address.index() as usize] = value;
+    }
+
+    fn set_word<A: address::WordAddress>(&mut self, address: A, value: u16) {
+        let [l, h] = value.to_le_bytes();
+        self.memory[address.index_l() as usize] = l;
+        self.memory[address.index_h() as usize] = h;
+    }
+
+    fn read(&self, start: u8, length: u8) -> Option<Vec<u8>> {
+        let start = start as usize;
+        let end = start + length as usize;
+        self.memory.get(start..end).map(Vec::from)
+    }
+
+    fn write(&mut self, start: u8, data: &[u8]) -> Option<()> {
+        let start = start as usize;
+        let end = start + data.len();
+        self.memory.get_mut(start..end)?.copy_from_slice(data);
+
+        let touches = |first: u8, last: u8| start <= last as usize && (first as usize) < end;
+        if touches(address::Acceleration.index(), address::GoalSpeed.index_h()) {
+            self.start_move();
+        }
+
+        // A new offset changes the present position straight away.
+        if touches(address::Ofs.index_l(), address::Ofs.index_h()) {
+            self.update(0.0);
+        }
+
+        // Writing 128 to torque enable makes the present position the midpoint.
+        if self.byte(address::TorqueEnable) == 128 {
+            let offset = (self.position - 2048.0).round() as i32;
+            let offset = (offset + 2048).rem_euclid(4096) - 2048;
+            self.set_word(address::Ofs, encode_signed(offset, OFFSET_SIGN_BIT));
+            self.set_byte(address::TorqueEnable, 0);
+            self.update(0.0);
+        }
+
+        Some(())
+    }
+
+    fn start_move(&mut self) {
+        let time = self.word(address::GoalTime);
+        let speed = self.word(address::GoalSpeed);
+        let goal = self.word(address::GoalPosition) as f32 + self.offset();
+
+        self.timed_speed =
+            (speed == 0 && time > 0).then(|| (goal - self.position).abs() / (time as f32 / 1000.0));
+    }
+
+    /// The `Ofs` register in steps, which is subtracted from the shaft position.
+    fn offset(&self) -> f32 {
+        decode_signed(self.word(address::Ofs), OFFSET_SIGN_BIT) as f32
+    }
+
+    fn update(&mut self, dt: f32) {
+        let torque = self.byte(address::TorqueEnable) != 0;
+        let acceleration = match self.byte(address::Acceleration) {
+            0 => f32::INFINITY,
+            x => x as f32 * 100.0,
+        };
+
+        let change = if acceleration.is_finite() {
+            acceleration * dt
+        } else {
+            f32::INFINITY
+        };
+
+        let mut distance = 0.0;
+        if !torque {
+            self.velocity = 0.0;
+        } else if self.byte(address::Mode) == 1 {
+            let target = decode_signed(self.word(address::GoalSpeed), SPEED_SIGN_BIT) as f32;
+            self.velocity = approach(self.velocity, target, change);
+            self.position = (self.position + self.velocity * dt).rem_euclid(4096.0);
+        } else {
+            distance = self.word(address::GoalPosition) as f32 + self.offset() - self.position;
+
+            let speed = match self.word(address::GoalSpeed) {
+                0 => self.timed_speed.unwrap_or(MAX_SPEED),
+                x => x as f32,
+            };
+            let stopping = (2.0 * acceleration * distance.abs()).sqrt();
+            let target = distance.signum() * speed.min(stopping);
+            self.velocity = approach(self.velocity, target, change);
+
+            let step = self.velocity * dt;
+            if step.abs() >= distance.abs() {
+                self.position += distance;
+                self.velocity = 0.0;
+                distance = 0.0;
+            } else {
+                self.position += step;
+                distance -= step;
+            }
+        }
+
+        let present = (self.position - self.offset()).round().rem_euclid(4096.0) as u16;
+        let moving = self.velocity != 0.0 || distance.abs() >= 0.5;
+        self.set_word(address::PresentPosition, present);
+        self.set_word(
+            address::PresentSpeed,
+            encode_signed(self.velocity.round() as i32, SPEED_SIGN_BIT),
+        );
+        self.set_word(
+            address::PresentLoad,
+            encode_signed(self.load, LOAD_SIGN_BIT),
+        );
+        self.set_byte(address::Moving, moving as u8);
+    }
+}
+
+fn approach(current: f32, target: f32, step: f32) -> f32 {
+    if (target - current).abs() <= step {
+        target
+    } else {
+        current + step * (target - current).signum()
+    }
+}
+
+#[derive(Debug)]
+enum Clock {
+    Real(Instant),
+    Manual,
+}
+
+/// An in-memory bus of simulated servos, usable anywhere a serial port is.
+///
+/// Packets written to the simulator are answered like real servos would, so code can be
+/// exercised without hardware. With [`Simulator::new`] the servos move in real time; with
+/// [`Simulator::with_manual_clock`] they only move when [`Simulator::step`] is called.
+#[derive(Debug)]
+pub struct Simulator {
+    servos: BTreeMap<u8, SimulatedServo>,
+    received: Vec<u8>,
+    pending: VecDeque<u8>,
+    clock: Clock,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Self {
+            servos: BTreeMap::new(),
+            received: Vec::new(),
+            pending: VecDeque::new(),
+            clock: Clock::Real(Instant::now()),
+        }
+    }
+
+    pub fn with_manual_clock() -> Self {
+        Self {
+            clock: Clock::Manual,
+            ..Self::new()
+        }
+    }
+
+    /// Add a servo with default settings, centered at position 2048.
+    pub fn add_servo(&mut self, id: ID) -> &mut SimulatedServo {
+        let id = id.value();
+        self.servos.insert(id, SimulatedServo::new(id));
+        self.servos.get_mut(&id).expect("Servo was just inserted.")
+    }
+
+    pub fn servo(&self, id: ID) -> Option<&SimulatedServo> {
+        self.servos.get(&id.value())
+    }
+
+    pub fn servo_mut(&mut self, id: ID) -> Option<&mut SimulatedServo> {
+        self.servos.get_mut(&id.value())
+    }
+
+    /// Advance every servo by `dt`.
+    pub fn step(&mut self, dt: Duration) {
+        for servo in self.servos.values_mut() {
+            servo.update(dt.as_secs_f32());
+        }
+    }
+
+    fn catch_up(&mut self) {
+        if let Clock::Real(last) = self.clock {
+            let now = Instant::now();
+            self.clock = Clock::Real(now);
+            self.step(now - last);
+        }
+    }
+
+    fn process(&mut self) {
+        loop {
+            // Drop anything before the packet header.
+            let start = self
+                .received
+                .windows(2)
+                .position(|pair| pair == [0xff, 0xff])
+                .unwrap_or(self.received.len().saturating_sub(1));
+            self.received.drain(..start);
+
+            let Some(&length) = self.received.get(3) else {
+                return;
+            };
+            let total = 4 + length as usize;
+            if self.received.len() < total {
+                return;
+            }
+
+            let packet: Vec<u8> = self.received.drain(..total).collect();
+            if length < 2 || crc(&packet[2..total - 1]) != packet[total - 1] {
+                continue;
+            }
+
+            self.handle(packet[2], packet[4], &packet[5..total - 1]);
+        }
+    }
+
+    fn handle(&mut self, id: u8, instruction: u8, parameters: &[u8]) {
+        if let (0x83, &[start, length, ref writes @ ..]) = (instruction, parameters) {
+            if length > 0 {
+                self.sync_write(start, length as usize, writes);
+            }
+            return;
+        }
+
+        if id == ID::broadcast().value() {
+            for servo in self.servos.values_mut() {
+                execute(servo, instruction, parameters);
+            }
+            return;
+        }
+
+        let Some(servo) = self.servos.get_mut(&id) else {
+            return;
+        };
+
+        let Some(payload) = execute(servo, instruction, parameters) else {
+            return;
+        };
+        let errors = servo.errors;
+
+        // Follow the servo if it was given a new id.
+        let new_id = servo.byte(address::ID);
+        if new_id != id {
+            if let Some(servo) = self.servos.remove(&id) {
+                self.servos.insert(new_id, servo);
+            }
+        }
+
+        let mut response = Vec::from([0xff, 0xff, id, payload.len() as u8 + 2, errors]);
+        response.extend(payload);
+        response.push(crc(&response[2..]));
+        self.pending.extend(response);
+    }
+
+    fn sync_write(&mut self, start: u8, length: usize, writes: &[u8]) {
+        for write in writes.chunks_exact(length + 1) {
+            if let Some(servo) = self.servos.get_mut(&write[0]) {
+                servo.write(start, &write[1..]);
+            }
+        }
+    }
+}
+
+/// Run an instruction against a servo, returning the response payload.
+fn execute(servo: &mut SimulatedServo, instruction: u8, parameters: &[u8]) -> Option<Vec<u8>> {
+    match (instruction, parameters) {
+        (0x01, []) => Some(Vec::new()),
+        (0x02, &[start, length]) => servo.read(start, length),
+        (0x03, [start, data @ ..]) if !data.is_empty() => {
+            servo.write(*start, data).map(|_| Vec::new())
+        }
+        _ => None,
+    }
+}
+
+impl Write for Simulator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.catch_up();
+        self.received.extend_from_slice(buf);
+        self.process();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Simulator {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        let count = buf.len().min(self.pending.len());
+        for (byte, value) in buf.iter_mut().zip(self.pending.drain(..count)) {
+            *byte = value;
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,429 @@
+use std::io::{self, BufRead, Write};
+
+use thiserror::Error;
+
+use crate::{
+    hardware::{
+        address::{self, Address, ReadRegion, Size},
+        ID,
+    },
+    serial::Port,
+    servo::{Assign, BaudRate, Servo, ServoError},
+    text,
+};
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+    #[error("IO Error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Snapshot format is invalid on line {0}.")]
+    Format(usize),
+    #[error("Registers differ after restoring: {0:?}")]
+    Verify(Vec<Difference>),
+    #[error("{name} cannot be restored to {value}.")]
+    Value { name: &'static str, value: u16 },
+}
+
+/// The raw value of one register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub index: u8,
+    pub name: &'static str,
+    pub value: u16,
+}
+
+/// A register whose value differs between two snapshots, or is missing from one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Difference {
+    pub index: u8,
+    pub name: &'static str,
+    pub left: Option<u16>,
+    pub right: Option<u16>,
+}
+
+/// Every register of a servo, read at one time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// In address order.
+    pub entries: Vec<Entry>,
+}
+
+impl Snapshot {
+    const HEADER: &'static str = "waveshare_serial_servo snapshot v1";
+
+    /// Read every register, using one read for each run of adjacent registers.
+    pub fn read(servo: &Servo, port: &mut impl Port) -> Result<Self, ServoError> {
+        let mut entries = Vec::new();
+        for run in runs(address::all()) {
+            let start = run[0].index();
+            let length = run.iter().map(|address| width(address.as_ref())).sum();
+            let region = ReadRegion::new(start, length).expect("Every register is readable.");
+            let response = servo.read(region, port)?;
+
+            for address in run {
+                let offset = (address.index() - start) as usize;
+                let value = match address.size() {
+                    Size::Byte => response.payload[offset] as u16,
+                    Size::Word => {
+                        u16::from_le_bytes([response.payload[offset], response.payload[offset + 1]])
+                    }
+                };
+                entries.push(Entry {
+                    index: address.index(),
+                    name: address.name(),
+                    value,
+                });
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, name: &str) -> Option<u16> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .map(|entry| entry.value)
+    }
+
+    /// Every register that differs from `other`, in address order.
+    pub fn diff(&self, other: &Snapshot) -> Vec<Difference> {
+        let value = |snapshot: &Snapshot, index: u8| {
+            snapshot
+                .entries
+                .iter()
+                .find(|entry| entry.index == index)
+                .map(|entry| entry.value)
+        };
+
+        address::all()
+            .map(|address| Difference {
+                index: address.index(),
+                name: address.name(),
+                left: value(self, address.index()),
+                right: value(other, address.index()),
+            })
+            .filter(|difference| difference.left != difference.right)
+            .collect()
+    }
+
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        writeln!(writer, "{}", Self::HEADER)?;
+        for entry in &self.entries {
+            writeln!(writer, "{} {} {}", entry.index, entry.name, entry.value)?;
+        }
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, SnapshotError> {
+        let mut entries: Vec<Entry> = Vec::new();
+        for line in text::lines_after(reader, Self::HEADER)?.ok_or(SnapshotError::Format(1))? {
+            let (number, line) = line?;
+            let entry = parse_entry(&line).ok_or(SnapshotError::Format(number))?;
+            if entries.last().is_some_and(|last| last.index >= entry.index) {
+                return Err(SnapshotError::Format(number));
+            }
+            entries.push(entry);
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let &[index, name, value] = line.split_whitespace().collect::<Vec<_>>().as_slice() else {
+        return None;
+    };
+
+    let address = address::address_from(index.parse().ok()?)?;
+    let value: u16 = value.parse().ok()?;
+    let valid = address.name() == name
+        && match address.size() {
+            Size::Byte => value <= u8::MAX as u16,
+            Size::Word => true,
+        };
+
+    valid.then_some(Entry {
+        index: address.index(),
+        name: address.name(),
+        value,
+    })
+}
+
+fn width(address: &dyn Address) -> u8 {
+    match address.size() {
+        Size::Byte => 1,
+        Size::Word => 2,
+    }
+}
+
+/// Group registers that directly follow one another.
+fn runs(addresses: impl Iterator<Item = Box<dyn Address>>) -> Vec<Vec<Box<dyn Address>>> {
+    let mut runs: Vec<Vec<Box<dyn Address>>> = Vec::new();
+    for address in addresses {
+        match runs.last_mut() {
+            Some(run)
+                if run
+                    .last()
+                    .is_some_and(|last| last.index() + width(last.as_ref()) == address.index()) =>
+            {
+                run.push(address)
+            }
+            _ => runs.push(Vec::from([address])),
+        }
+    }
+    runs
+}
+
+/// Which registers [`restore`] writes, besides the ones that are always restored.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestoreOptions {
+    pub id: bool,
+}
+
+/// Write the EEPROM settings of `snapshot` to a servo, then read them back to check they were
+/// kept. The servo's id is only written when chosen in `options`, and the baud rate never is.
+/// See [`restore_baud_rate`].
+///
+/// Every value is checked as [`Assign::set_register`] would before anything is written, since
+/// snapshot files may have been edited by hand. A new id is written last, and `servo` is
+/// updated to it.
+pub fn restore(
+    servo: &mut Servo,
+    snapshot: &Snapshot,
+    options: RestoreOptions,
+    port: &mut impl Port,
+) -> Result<(), SnapshotError> {
+    let restored: Vec<Entry> = snapshot
+        .entries
+        .iter()
+        .copied()
+        .filter(|entry| {
+            address::is_eeprom(entry.index)
+                && address::writeable_address_from(entry.index).is_some()
+                && entry.index != address::ID.index()
+                && entry.index != address::BaudRate.index()
+        })
+        .collect();
+
+    let mut assign = Assign::new();
+    for entry in &restored {
+        let address = address::address_from(entry.index).expect("Entries are registers.");
+        assign
+            .set_register(address.as_ref(), entry.value)
+            .map_err(|_| invalid(entry.name, entry.value))?;
+    }
+
+    let id = match (options.id, snapshot.get(address::ID.name())) {
+        (true, Some(value)) => Some(
+            u8::try_from(value)
+                .ok()
+                .and_then(|id| ID::single(id).ok())
+                .ok_or(invalid(address::ID.name(), value))?,
+        ),
+        _ => None,
+    };
+
+    servo.write_eeprom(&assign, port)?;
+    if let Some(id) = id.filter(|&id| id != servo.id()) {
+        servo.write_id(id, port)?;
+    }
+
+    let after = Snapshot::read(servo, port)?;
+    let mismatches: Vec<Difference> = snapshot
+        .diff(&after)
+        .into_iter()
+        .filter(|difference| restored.iter().any(|entry| entry.index == difference.index))
+        .collect();
+    if !mismatches.is_empty() {
+        return Err(SnapshotError::Verify(mismatches));
+    }
+
+    Ok(())
+}
+
+/// Write the baud rate saved in `snapshot`, when it differs from the servo's, then check it
+/// at the new rate. `switch` moves the port to the new rate, as for [`Servo::write_baud_rate`].
+pub fn restore_baud_rate<P: Port>(
+    servo: &Servo,
+    snapshot: &Snapshot,
+    port: &mut P,
+    switch: impl FnOnce(&mut P, BaudRate) -> io::Result<()>,
+) -> Result<(), SnapshotError> {
+    let Some(value) = snapshot.get(address::BaudRate.name()) else {
+        return Ok(());
+    };
+    let baud = u8::try_from(value)
+        .ok()
+        .and_then(BaudRate::n)
+        .ok_or(invalid(address::BaudRate.name(), value))?;
+
+    if servo.read_baud_rate(port)? == baud {
+        return Ok(());
+    }
+    servo.write_baud_rate(baud, port, switch)?;
+
+    let after = servo.read_byte(address::BaudRate, port)?;
+    if after != baud as u8 {
+        return Err(SnapshotError::Verify(Vec::from([Difference {
+            index: address::BaudRate.index(),
+            name: address::BaudRate.name(),
+            left: Some(value),
+            right: Some(after as u16),
+        }])));
+    }
+
+    Ok(())
+}
+
+fn invalid(name: &'static str, value: u16) -> SnapshotError {
+    SnapshotError::Value { name, value }
+}
+
+#[cfg(test)]
+mod tests {
+    use angle::Deg;
+
+    use super::*;
+    use crate::sim::Simulator;
+
+    fn servo(id: u8) -> Servo {
+        Servo::new(ID::single(id).unwrap())
+    }
+
+    /// A bus with servo 1 configured away from the defaults, and servo 2 left at them.
+    fn configured() -> Simulator {
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(ID::single(1).unwrap());
+        sim.add_servo(ID::single(2).unwrap());
+
+        let configured = servo(1);
+        configured
+            .write_angle_limits(Deg(45.0), Deg(315.0), &mut sim)
+            .unwrap();
+        configured.write_offset(Deg(-10.0), &mut sim).unwrap();
+        sim
+    }
+
+    fn eeprom_differences(left: &Snapshot, right: &Snapshot) -> Vec<&'static str> {
+        left.diff(right)
+            .into_iter()
+            .filter(|difference| address::is_eeprom(difference.index))
+            .map(|difference| difference.name)
+            .collect()
+    }
+
+    #[test]
+    fn backup_diff_and_restore_round_trip() {
+        let mut sim = configured();
+        let backup = Snapshot::read(&servo(1), &mut sim).unwrap();
+        assert_eq!(backup.get("MinAngleLimit"), Some(512));
+        assert_eq!(backup.get("Ofs"), Some(0x800 | 114));
+
+        let mut file = Vec::new();
+        backup.save(&mut file).unwrap();
+        let loaded = Snapshot::load(file.as_slice()).unwrap();
+        assert_eq!(loaded, backup);
+
+        let before = Snapshot::read(&servo(2), &mut sim).unwrap();
+        assert_eq!(
+            eeprom_differences(&loaded, &before),
+            ["ID", "MinAngleLimit", "MaxAngleLimit", "Ofs"]
+        );
+
+        let mut target = servo(2);
+        restore(&mut target, &loaded, RestoreOptions::default(), &mut sim).unwrap();
+        assert_eq!(target.id().value(), 2);
+
+        let after = Snapshot::read(&target, &mut sim).unwrap();
+        assert_eq!(eeprom_differences(&loaded, &after), ["ID"]);
+        assert_eq!(after.get("Lock"), Some(1));
+    }
+
+    #[test]
+    fn restore_can_move_the_id() {
+        let mut sim = configured();
+        let backup = Snapshot::read(&servo(1), &mut sim).unwrap();
+        sim.servo_mut(ID::single(1).unwrap())
+            .unwrap()
+            .set_position(0.0);
+        let mut file = Vec::new();
+        backup.save(&mut file).unwrap();
+
+        // Free id 1 up for servo 2 by moving the original servo out of the way.
+        servo(1).write_id(ID::single(3).unwrap(), &mut sim).unwrap();
+
+        let mut target = servo(2);
+        let options = RestoreOptions { id: true };
+        restore(&mut target, &backup, options, &mut sim).unwrap();
+        assert_eq!(target.id().value(), 1);
+        assert!(target.ping(&mut sim).is_ok());
+    }
+
+    #[test]
+    fn restore_rejects_invalid_values_before_writing() {
+        let mut sim = configured();
+        let text = format!(
+            "{}\n9 MinAngleLimit 100\n11 MaxAngleLimit 5000\n",
+            Snapshot::HEADER
+        );
+        let snapshot = Snapshot::load(text.as_bytes()).unwrap();
+
+        let mut target = servo(2);
+        let result = restore(&mut target, &snapshot, RestoreOptions::default(), &mut sim);
+        assert!(matches!(
+            result,
+            Err(SnapshotError::Value {
+                name: "MaxAngleLimit",
+                value: 5000
+            })
+        ));
+
+        let untouched = sim.servo(ID::single(2).unwrap()).unwrap();
+        assert_eq!(untouched.word(address::MinAngleLimit), 0);
+    }
+
+    #[test]
+    fn restore_baud_rate_checks_at_the_new_rate() {
+        let mut sim = configured();
+        let text = format!("{}\n6 BaudRate 4\n", Snapshot::HEADER);
+        let snapshot = Snapshot::load(text.as_bytes()).unwrap();
+
+        let mut switched = None;
+        restore_baud_rate(&servo(2), &snapshot, &mut sim, |_, baud| {
+            switched = Some(baud);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(switched, Some(BaudRate::B115200));
+        let restored = sim.servo(ID::single(2).unwrap()).unwrap();
+        assert_eq!(restored.byte(address::BaudRate), 4);
+        assert_eq!(restored.byte(address::Lock), 1);
+    }
+
+    #[test]
+    fn load_rejects_unordered_or_misnamed_entries() {
+        let text = format!(
+            "{}\n11 MaxAngleLimit 4095\n9 MinAngleLimit 0\n",
+            Snapshot::HEADER
+        );
+        assert!(matches!(
+            Snapshot::load(text.as_bytes()),
+            Err(SnapshotError::Format(3))
+        ));
+
+        let text = format!("{}\n9 MaxAngleLimit 0\n", Snapshot::HEADER);
+        assert!(matches!(
+            Snapshot::load(text.as_bytes()),
+            Err(SnapshotError::Format(2))
+        ));
+
+        assert!(matches!(
+            Snapshot::load("not a snapshot\n".as_bytes()),
+            Err(SnapshotError::Format(1))
+        ));
+    }
+}
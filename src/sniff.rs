@@ -0,0 +1,485 @@
+use std::{
+    fmt::{self, Display},
+    io::{self, Read, Write},
+    time::Instant,
+};
+
+use crate::{
+    crc,
+    hardware::{
+        address::{self, Size},
+        decode_signed, sign_bit, DriverErrors, ID,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the host to the servos.
+    Transmit,
+    /// From a servo to the host.
+    Receive,
+}
+
+/// One register's value, as found in a write or read.
+#[derive(Debug, Clone, Copy)]
+pub struct Register {
+    pub index: u8,
+    /// `None` for unknown addresses, or when only part of a word is present.
+    pub name: Option<&'static str>,
+    pub raw: u16,
+    /// The raw value, with the sign applied for sign-magnitude registers.
+    pub value: i32,
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{name}={}", self.value),
+            None => write!(f, "[{}]={:#04x}", self.index, self.raw),
+        }
+    }
+}
+
+/// Split `data`, which starts at register `start`, into named registers.
+pub fn registers(start: u8, data: &[u8]) -> Vec<Register> {
+    let mut registers = Vec::new();
+    let mut offset = 0;
+
+    while let Some(&low) = data.get(offset) {
+        let index = start.wrapping_add(offset as u8);
+        let address = address::address_from(index).filter(|address| address.index() == index);
+
+        let (name, raw, width) = match (address, data.get(offset + 1)) {
+            (Some(address), Some(&high)) if matches!(address.size(), Size::Word) => {
+                (Some(address.name()), u16::from_le_bytes([low, high]), 2)
+            }
+            (Some(address), _) if matches!(address.size(), Size::Byte) => {
+                (Some(address.name()), low as u16, 1)
+            }
+            _ => (None, low as u16, 1),
+        };
+
+        registers.push(Register {
+            index,
+            name,
+            raw,
+            value: signed(index, raw),
+        });
+        offset += width;
+    }
+
+    registers
+}
+
+/// Apply the sign bit of registers stored as sign and magnitude.
+fn signed(index: u8, raw: u16) -> i32 {
+    match sign_bit(index) {
+        Some(sign_bit) => decode_signed(raw, sign_bit),
+        None => raw as i32,
+    }
+}
+
+/// A decoded packet, or bytes that could not be decoded.
+#[derive(Debug, Clone)]
+pub enum Message {
+    Ping {
+        id: u8,
+    },
+    Read {
+        id: u8,
+        start: u8,
+        length: u8,
+    },
+    Write {
+        id: u8,
+        start: u8,
+        data: Vec<u8>,
+    },
+    SyncWrite {
+        start: u8,
+        writes: Vec<(u8, Vec<u8>)>,
+    },
+    /// Any other instruction.
+    Instruction {
+        id: u8,
+        instruction: u8,
+        parameters: Vec<u8>,
+    },
+    Status {
+        id: u8,
+        errors: Option<DriverErrors>,
+        /// The register the payload starts at, when it answers a read that was seen.
+        start: Option<u8>,
+        payload: Vec<u8>,
+    },
+    /// A packet with a bad length or crc.
+    Corrupt(Vec<u8>),
+    /// Bytes outside of any packet.
+    Noise(Vec<u8>),
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ping { id } => write!(f, "Ping {}", Id(*id)),
+            Self::Read { id, start, length } => {
+                write!(f, "Read {}: {length} bytes from ", Id(*id))?;
+                match address::address_from(*start).filter(|address| address.index() == *start) {
+                    Some(address) => write!(f, "{}", address.name()),
+                    None => write!(f, "[{start}]"),
+                }
+            }
+            Self::Write { id, start, data } => {
+                write!(f, "Write {}:", Id(*id))?;
+                write_registers(f, *start, data)
+            }
+            Self::SyncWrite { start, writes } => {
+                write!(f, "SyncWrite")?;
+                for (id, data) in writes {
+                    write!(f, " {} {{", Id(*id))?;
+                    write_registers(f, *start, data)?;
+                    write!(f, " }}")?;
+                }
+                Ok(())
+            }
+            Self::Instruction {
+                id,
+                instruction,
+                parameters,
+            } => write!(
+                f,
+                "Instruction {instruction:#04x} {}: {}",
+                Id(*id),
+                Hex(parameters)
+            ),
+            Self::Status {
+                id,
+                errors,
+                start,
+                payload,
+            } => {
+                write!(f, "Status {}: ", Id(*id))?;
+                match errors {
+                    Some(errors) => write!(f, "{errors}")?,
+                    None => write!(f, "Ok")?,
+                }
+                match (start, payload.is_empty()) {
+                    (_, true) => Ok(()),
+                    (Some(start), false) => write_registers(f, *start, payload),
+                    (None, false) => write!(f, " {}", Hex(payload)),
+                }
+            }
+            Self::Corrupt(bytes) => write!(f, "Corrupt packet: {}", Hex(bytes)),
+            Self::Noise(bytes) => write!(f, "Noise: {}", Hex(bytes)),
+        }
+    }
+}
+
+fn write_registers(f: &mut fmt::Formatter<'_>, start: u8, data: &[u8]) -> fmt::Result {
+    registers(start, data)
+        .iter()
+        .try_for_each(|register| write!(f, " {register}"))
+}
+
+struct Id(u8);
+
+impl Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match ID::try_from(self.0) {
+            Ok(ID::Broadcast) => write!(f, "broadcast"),
+            _ => write!(f, "id {}", self.0),
+        }
+    }
+}
+
+struct Hex<'a>(&'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.0.iter().map(|byte| format!("{byte:02x}")).collect();
+        write!(f, "[{}]", bytes.join(" "))
+    }
+}
+
+/// Decodes the bytes sent each way on a bus into messages.
+///
+/// Bytes may arrive in any sized pieces. A read request that was seen is remembered, so the
+/// status packet answering it can be decoded into named registers.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    transmitted: Vec<u8>,
+    received: Vec<u8>,
+    pending_read: Option<(u8, u8)>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add bytes seen in `direction`, returning every message they complete.
+    pub fn push(&mut self, direction: Direction, bytes: &[u8]) -> Vec<Message> {
+        self.buffer(direction).extend_from_slice(bytes);
+
+        let mut messages = Vec::new();
+        while let Some(message) = next_packet(self.buffer(direction)) {
+            messages.push(match message {
+                Ok(packet) => self.decode(direction, packet),
+                Err(message) => message,
+            });
+        }
+        messages
+    }
+
+    fn buffer(&mut self, direction: Direction) -> &mut Vec<u8> {
+        match direction {
+            Direction::Transmit => &mut self.transmitted,
+            Direction::Receive => &mut self.received,
+        }
+    }
+
+    fn decode(&mut self, direction: Direction, packet: Packet) -> Message {
+        let Packet {
+            id,
+            code,
+            parameters,
+        } = packet;
+
+        if direction == Direction::Receive {
+            let start = self
+                .pending_read
+                .take()
+                .filter(|&(read_id, _)| read_id == id)
+                .map(|(_, start)| start);
+            return Message::Status {
+                id,
+                errors: DriverErrors::from_byte(code),
+                start,
+                payload: parameters,
+            };
+        }
+
+        match (code, parameters.as_slice()) {
+            (0x01, []) => Message::Ping { id },
+            (0x02, &[start, length]) => {
+                self.pending_read = Some((id, start));
+                Message::Read { id, start, length }
+            }
+            (0x03, &[start, ref data @ ..]) => Message::Write {
+                id,
+                start,
+                data: Vec::from(data),
+            },
+            (0x83, &[start, length, ref writes @ ..])
+                if length > 0 && writes.len() % (length as usize + 1) == 0 =>
+            {
+                Message::SyncWrite {
+                    start,
+                    writes: writes
+                        .chunks_exact(length as usize + 1)
+                        .map(|write| (write[0], Vec::from(&write[1..])))
+                        .collect(),
+                }
+            }
+            _ => Message::Instruction {
+                id,
+                instruction: code,
+                parameters,
+            },
+        }
+    }
+}
+
+struct Packet {
+    id: u8,
+    /// The instruction, or the status of a response.
+    code: u8,
+    parameters: Vec<u8>,
+}
+
+/// Take the next packet, or the noise before it, off the front of `buffer`.
+fn next_packet(buffer: &mut Vec<u8>) -> Option<Result<Packet, Message>> {
+    let start = buffer
+        .windows(2)
+        .position(|pair| pair == [0xff, 0xff])
+        .unwrap_or(buffer.len().saturating_sub(1));
+    if start > 0 {
+        return Some(Err(Message::Noise(buffer.drain(..start).collect())));
+    }
+
+    let &length = buffer.get(3)?;
+    let total = 4 + length as usize;
+    if buffer.len() < total {
+        return None;
+    }
+
+    let packet: Vec<u8> = buffer.drain(..total).collect();
+    if length < 2 || crc(&packet[2..total - 1]) != packet[total - 1] {
+        return Some(Err(Message::Corrupt(packet)));
+    }
+
+    Some(Ok(Packet {
+        id: packet[2],
+        code: packet[4],
+        parameters: Vec::from(&packet[5..total - 1]),
+    }))
+}
+
+/// Decode a complete capture of the bytes sent in one direction.
+pub fn decode(direction: Direction, bytes: &[u8]) -> Vec<Message> {
+    Decoder::new().push(direction, bytes)
+}
+
+/// A port that decodes everything passing through it, and writes each message to a log with
+/// the time since the sniffer was created.
+///
+/// Failing to write the log never fails a read or write, since the bytes have already passed
+/// through the port by then. Check [`Sniffer::take_log_error`] to find out the log is broken.
+#[derive(Debug)]
+pub struct Sniffer<P, L> {
+    port: P,
+    log: L,
+    decoder: Decoder,
+    start: Instant,
+    log_error: Option<io::Error>,
+}
+
+impl<P, L: Write> Sniffer<P, L> {
+    pub fn new(port: P, log: L) -> Self {
+        Self {
+            port,
+            log,
+            decoder: Decoder::new(),
+            start: Instant::now(),
+            log_error: None,
+        }
+    }
+
+    pub fn into_inner(self) -> (P, L) {
+        (self.port, self.log)
+    }
+
+    /// The first error writing the log since the last call, if any.
+    pub fn take_log_error(&mut self) -> Option<io::Error> {
+        self.log_error.take()
+    }
+
+    fn record(&mut self, direction: Direction, bytes: &[u8]) {
+        if let Err(e) = self.write_log(direction, bytes) {
+            self.log_error.get_or_insert(e);
+        }
+    }
+
+    fn write_log(&mut self, direction: Direction, bytes: &[u8]) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        let arrow = match direction {
+            Direction::Transmit => "->",
+            Direction::Receive => "<-",
+        };
+
+        for message in self.decoder.push(direction, bytes) {
+            writeln!(self.log, "{time:>12.6} {arrow} {message}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<P: Read, L: Write> Read for Sniffer<P, L> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.port.read(buf)?;
+        self.record(Direction::Receive, &buf[..count]);
+        Ok(count)
+    }
+}
+
+impl<P: Write, L: Write> Write for Sniffer<P, L> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.port.write(buf)?;
+        self.record(Direction::Transmit, &buf[..count]);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()?;
+        self.log.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::{address::Address, encode_signed, LOAD_SIGN_BIT, OFFSET_SIGN_BIT},
+        servo::Servo,
+        sim::Simulator,
+    };
+
+    /// A log whose next write fails once.
+    #[derive(Default)]
+    struct Flaky {
+        lines: Vec<u8>,
+        fail: bool,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if std::mem::take(&mut self.fail) {
+                return Err(io::ErrorKind::StorageFull.into());
+            }
+            self.lines.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logging_resumes_after_a_log_error() {
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id);
+        let mut sniffer = Sniffer::new(
+            sim,
+            Flaky {
+                fail: true,
+                ..Flaky::default()
+            },
+        );
+
+        // The ping's own log line is lost, but its response is still decoded and logged.
+        Servo::new(id).ping(&mut sniffer).unwrap();
+        let error = sniffer.take_log_error().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+
+        Servo::new(id).ping(&mut sniffer).unwrap();
+        assert!(sniffer.take_log_error().is_none());
+
+        let (_, log) = sniffer.into_inner();
+        let log = String::from_utf8(log.lines).unwrap();
+        let arrows: Vec<&str> = log
+            .lines()
+            .map(|line| line.split_whitespace().nth(1).unwrap())
+            .collect();
+        assert_eq!(arrows, ["<-", "->", "<-"]);
+    }
+
+    #[test]
+    fn sign_magnitude_registers_are_signed() {
+        let mut data = Vec::new();
+        data.extend(encode_signed(-20, OFFSET_SIGN_BIT).to_le_bytes());
+        let decoded = registers(address::Ofs.index(), &data);
+        assert_eq!(decoded[0].name, Some("Ofs"));
+        assert_eq!(decoded[0].value, -20);
+
+        let mut data = Vec::new();
+        data.extend(encode_signed(-100, LOAD_SIGN_BIT).to_le_bytes());
+        let decoded = registers(address::PresentLoad.index(), &data);
+        assert_eq!(decoded[0].value, -100);
+
+        // Unsigned registers keep their raw value, even with the top bit set.
+        let decoded = registers(address::GoalPosition.index(), &[0x00, 0x80]);
+        assert_eq!(decoded[0].value, 0x8000);
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    hardware::{DriverErrors, ID},
+    serial::Port,
+    servo::{Assign, Servo, ServoError, StatusPolicy, Torque, TorqueLimit},
+    telemetry::Sample,
+};
+
+/// What the supervisor does about a condition, from least to most severe.
+///
+/// Actions are ordered by severity, so a lower torque limit is more severe than a higher one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Only report the event.
+    Warn,
+    /// Lower the servo's torque limit.
+    ReduceTorque(TorqueLimit),
+    /// Disable torque on the servo.
+    TorqueOff,
+    /// Disable torque on every servo on the bus.
+    StopAll,
+}
+
+impl Action {
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Warn => 0,
+            Self::ReduceTorque(_) => 1,
+            Self::TorqueOff => 2,
+            Self::StopAll => 3,
+        }
+    }
+}
+
+impl Ord for Action {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::ReduceTorque(limit), Self::ReduceTorque(other)) => other.cmp(limit),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Action {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A threshold, and the action taken once it is reached.
+#[derive(Debug, Clone, Copy)]
+pub struct Limit<T> {
+    pub value: T,
+    pub action: Action,
+}
+
+/// A threshold that must be exceeded for some time before the action is taken.
+#[derive(Debug, Clone, Copy)]
+pub struct SustainedLimit<T> {
+    pub value: T,
+    pub duration: Duration,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    /// Temperature limits in degrees celsius. The most severe limit reached applies.
+    pub temperature: Vec<Limit<u8>>,
+    /// The allowed voltage range in 0.1V, inclusive.
+    pub voltage: (u8, u8),
+    pub voltage_action: Action,
+    /// Load magnitude in 0.1% of the maximum.
+    pub load: Option<SustainedLimit<u16>>,
+    /// Current in 6.5mA.
+    pub current: Option<SustainedLimit<u16>>,
+    /// Taken when a servo reports a status that fails `driver_status`.
+    pub driver_errors: Action,
+    /// Which status bits count as driver errors. [`StatusPolicy::Ignore`] disables the check.
+    pub driver_status: StatusPolicy,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            temperature: Vec::from([
+                Limit {
+                    value: 55,
+                    action: Action::Warn,
+                },
+                Limit {
+                    value: 65,
+                    action: Action::ReduceTorque(TorqueLimit::new(0.5)),
+                },
+                Limit {
+                    value: 70,
+                    action: Action::TorqueOff,
+                },
+            ]),
+            voltage: (60, 140),
+            voltage_action: Action::StopAll,
+            load: Some(SustainedLimit {
+                value: 800,
+                duration: Duration::from_secs(2),
+                action: Action::ReduceTorque(TorqueLimit::new(0.5)),
+            }),
+            current: None,
+            driver_errors: Action::Warn,
+            driver_status: StatusPolicy::FailOnKnown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Condition {
+    /// In degrees celsius.
+    Temperature(u8),
+    /// In 0.1V.
+    Voltage(u8),
+    /// Signed, in 0.1% of the maximum.
+    Load(i32),
+    /// In 6.5mA.
+    Current(u16),
+    Driver(DriverErrors),
+}
+
+impl Condition {
+    fn kind(&self) -> Kind {
+        match self {
+            Self::Temperature(_) => Kind::Temperature,
+            Self::Voltage(_) => Kind::Voltage,
+            Self::Load(_) => Kind::Load,
+            Self::Current(_) => Kind::Current,
+            Self::Driver(_) => Kind::Driver,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Temperature,
+    Voltage,
+    Load,
+    Current,
+    Driver,
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub id: ID,
+    pub time: Instant,
+    pub condition: Condition,
+    /// The action taken, or `None` when the condition has cleared.
+    pub action: Option<Action>,
+}
+
+/// Watches telemetry against a [`SupervisorConfig`], and acts when limits are crossed.
+///
+/// An event is emitted when a condition first crosses a limit, escalates to a more severe
+/// action, or clears. Clearing never undoes an action, so torque stays limited or off until the
+/// application restores it.
+#[derive(Debug)]
+pub struct Supervisor {
+    config: SupervisorConfig,
+    active: HashMap<(u8, Kind), (Action, Condition)>,
+    exceeded_since: HashMap<(u8, Kind), Instant>,
+}
+
+impl Supervisor {
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            active: HashMap::new(),
+            exceeded_since: HashMap::new(),
+        }
+    }
+
+    /// Check a sample, returning the events it caused without taking any action.
+    pub fn check(&mut self, sample: &Sample) -> Vec<Event> {
+        let config = &self.config;
+
+        let temperature = config
+            .temperature
+            .iter()
+            .filter(|limit| sample.temperature >= limit.value)
+            .map(|limit| limit.action)
+            .max();
+
+        let (min, max) = config.voltage;
+        let voltage = (!(min..=max).contains(&sample.voltage)).then_some(config.voltage_action);
+
+        let (load, current) = (config.load, config.current);
+        let load = load.and_then(|limit| {
+            let exceeded = sample.load.unsigned_abs() >= limit.value as u32;
+            self.sustained(sample, Kind::Load, exceeded, limit)
+        });
+
+        let current = current.and_then(|limit| {
+            let exceeded = sample.current >= limit.value;
+            self.sustained(sample, Kind::Current, exceeded, limit)
+        });
+
+        let errors = sample
+            .errors
+            .filter(|&errors| self.config.driver_status.fails(errors));
+        let driver = errors.map(|_| self.config.driver_errors);
+
+        let mut checks = Vec::from([
+            (Condition::Temperature(sample.temperature), temperature),
+            (Condition::Voltage(sample.voltage), voltage),
+            (Condition::Load(sample.load), load),
+            (Condition::Current(sample.current), current),
+        ]);
+
+        // Once the errors clear, report the ones that were last seen.
+        let errors = errors.map(Condition::Driver).or_else(|| {
+            self.active
+                .get(&(sample.id.value(), Kind::Driver))
+                .map(|&(_, condition)| condition)
+        });
+        if let Some(condition) = errors {
+            checks.push((condition, driver));
+        }
+
+        checks
+            .into_iter()
+            .filter_map(|(condition, action)| self.transition(sample, condition, action))
+            .collect()
+    }
+
+    /// Take the action of an event. Cleared events do nothing.
+    pub fn apply(&self, event: &Event, port: &mut impl Port) -> Result<(), ServoError> {
+        let servo = Servo::new(event.id);
+        match event.action {
+            None | Some(Action::Warn) => Ok(()),
+            Some(Action::ReduceTorque(limit)) => servo.write(&Assign::new().with(limit), port),
+            Some(Action::TorqueOff) => servo.write(&Assign::new().with(Torque::Disabled), port),
+            Some(Action::StopAll) => {
+                Servo::new(ID::broadcast()).write(&Assign::new().with(Torque::Disabled), port)
+            }
+        }
+    }
+
+    /// Check a sample, and take the action of every event it caused.
+    pub fn supervise(
+        &mut self,
+        sample: &Sample,
+        port: &mut impl Port,
+    ) -> Result<Vec<Event>, ServoError> {
+        let events = self.check(sample);
+        for event in &events {
+            self.apply(event, port)?;
+        }
+        Ok(events)
+    }
+
+    fn sustained<T>(
+        &mut self,
+        sample: &Sample,
+        kind: Kind,
+        exceeded: bool,
+        limit: SustainedLimit<T>,
+    ) -> Option<Action> {
+        let key = (sample.id.value(), kind);
+        if !exceeded {
+            self.exceeded_since.remove(&key);
+            return None;
+        }
+
+        let since = *self.exceeded_since.entry(key).or_insert(sample.time);
+        (sample.time - since >= limit.duration).then_some(limit.action)
+    }
+
+    fn transition(
+        &mut self,
+        sample: &Sample,
+        condition: Condition,
+        action: Option<Action>,
+    ) -> Option<Event> {
+        let key = (sample.id.value(), condition.kind());
+        let previous = self.active.get(&key).map(|&(action, _)| action);
+
+        let changed = match (previous, action) {
+            (None, None) => false,
+            (Some(_), None) => {
+                self.active.remove(&key);
+                true
+            }
+            (Some(previous), Some(action)) if action <= previous => false,
+            (_, Some(action)) => {
+                self.active.insert(key, (action, condition));
+                true
+            }
+        };
+
+        changed.then_some(Event {
+            id: sample.id,
+            time: sample.time,
+            condition,
+            action,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::servo::Position;
+
+    fn sample(temperature: u8) -> Sample {
+        Sample {
+            id: ID::single(1).unwrap(),
+            time: Instant::now(),
+            position: Position::new_raw(2048).unwrap(),
+            speed: 0,
+            load: 0,
+            voltage: 120,
+            temperature,
+            current: 0,
+            moving: false,
+            errors: None,
+        }
+    }
+
+    #[test]
+    fn lower_torque_limits_are_more_severe() {
+        let half = Action::ReduceTorque(TorqueLimit::new(0.5));
+        let quarter = Action::ReduceTorque(TorqueLimit::new(0.25));
+
+        assert!(quarter > half);
+        assert!(Action::Warn < quarter);
+        assert!(half < Action::TorqueOff);
+        assert!(Action::TorqueOff < Action::StopAll);
+        assert_eq!(
+            [half, quarter, Action::Warn].into_iter().max(),
+            Some(quarter)
+        );
+    }
+
+    #[test]
+    fn torque_reductions_escalate() {
+        let half = Action::ReduceTorque(TorqueLimit::new(0.5));
+        let quarter = Action::ReduceTorque(TorqueLimit::new(0.25));
+        let mut supervisor = Supervisor::new(SupervisorConfig {
+            temperature: Vec::from([
+                Limit {
+                    value: 60,
+                    action: half,
+                },
+                Limit {
+                    value: 65,
+                    action: quarter,
+                },
+            ]),
+            ..SupervisorConfig::default()
+        });
+
+        let actions = |events: Vec<Event>| -> Vec<Option<Action>> {
+            events.iter().map(|event| event.action).collect()
+        };
+        assert_eq!(actions(supervisor.check(&sample(62))), [Some(half)]);
+        assert_eq!(actions(supervisor.check(&sample(66))), [Some(quarter)]);
+        // Cooling to the first limit does not relax the action.
+        assert_eq!(actions(supervisor.check(&sample(62))), []);
+        assert_eq!(actions(supervisor.check(&sample(40))), [None]);
+    }
+
+    #[test]
+    fn driver_errors_follow_the_status_policy() {
+        let mut supervisor = Supervisor::new(SupervisorConfig::default());
+
+        let mut unknown = sample(40);
+        unknown.errors = Some(DriverErrors::new(0x40));
+        assert!(supervisor.check(&unknown).is_empty());
+
+        let mut overload = sample(40);
+        overload.errors = Some(DriverErrors::new(0x20));
+        let events = supervisor.check(&overload);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].condition, Condition::Driver(_)));
+        assert_eq!(events[0].action, Some(Action::Warn));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    io::{BufRead, Write},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{
+    hardware::ID,
+    serial::Port,
+    servo::{Assign, GoalTime, Position, Servo, ServoError, Torque},
+    text,
+};
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("IO Error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Recording format is invalid on line {0}.")]
+    Format(usize),
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+    #[error("The recording has servos {recorded:?}, but was given {given:?}.")]
+    Servos { recorded: Vec<ID>, given: Vec<ID> },
+}
+
+/// The positions of every recorded servo at one point in time.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Time since the start of the recording.
+    pub time: Duration,
+    /// One position per servo, in the order of [`Recording::ids`].
+    pub positions: Vec<Position>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub ids: Vec<ID>,
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    const HEADER: &'static str = "waveshare_serial_servo recording v1";
+
+    /// Write the recording as text.
+    ///
+    /// The first line is a header, the second lists the servo ids, and each following line is
+    /// one frame: the time in milliseconds followed by the raw position of each servo. Frame
+    /// times never go backwards.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), RecordingError> {
+        writeln!(writer, "{}", Self::HEADER)?;
+
+        let ids: Vec<String> = self.ids.iter().map(|id| id.value().to_string()).collect();
+        writeln!(writer, "{}", ids.join(" "))?;
+
+        for frame in &self.frames {
+            write!(writer, "{}", frame.time.as_millis())?;
+            for position in &frame.positions {
+                write!(writer, " {}", position.value())?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, RecordingError> {
+        let mut lines =
+            text::lines_after(reader, Self::HEADER)?.ok_or(RecordingError::Format(1))?;
+
+        let (number, line) = lines.next().ok_or(RecordingError::Format(2))??;
+        let ids = line
+            .split_whitespace()
+            .map(|id| id.parse().ok().and_then(|id| ID::single(id).ok()))
+            .collect::<Option<Vec<ID>>>()
+            .ok_or(RecordingError::Format(number))?;
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let (number, line) = line?;
+            let frame = parse_frame(&line, ids.len()).ok_or(RecordingError::Format(number))?;
+            if frames
+                .last()
+                .is_some_and(|last: &Frame| last.time > frame.time)
+            {
+                return Err(RecordingError::Format(number));
+            }
+            frames.push(frame);
+        }
+
+        Ok(Self { ids, frames })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map_or(Duration::ZERO, |frame| frame.time)
+    }
+}
+
+fn parse_frame(line: &str, count: usize) -> Option<Frame> {
+    let mut parts = line.split_whitespace();
+
+    let time = Duration::from_millis(parts.next()?.parse().ok()?);
+    let positions = parts
+        .map(|part| Position::new_raw(part.parse().ok()?).ok())
+        .collect::<Option<Vec<Position>>>()?;
+
+    (positions.len() == count).then_some(Frame { time, positions })
+}
+
+/// Disable torque on each servo, then sample their present positions every `period` for
+/// `duration`, so the joints can be posed by hand.
+pub fn record(
+    servos: &[Servo],
+    period: Duration,
+    duration: Duration,
+    port: &mut impl Port,
+) -> Result<Recording, ServoError> {
+    record_while(servos, period, port, |frame| frame.time < duration)
+}
+
+/// Like [`record`], but keeps sampling until `keep_going` returns false for the latest frame.
+pub fn record_while<F: FnMut(&Frame) -> bool>(
+    servos: &[Servo],
+    period: Duration,
+    port: &mut impl Port,
+    mut keep_going: F,
+) -> Result<Recording, ServoError> {
+    let torque_off = Assign::new().with(Torque::Disabled);
+    for servo in servos {
+        servo.write(&torque_off, port)?;
+    }
+
+    let mut recording = Recording {
+        ids: servos.iter().map(Servo::id).collect(),
+        frames: Vec::new(),
+    };
+
+    let start = Instant::now();
+    let mut deadline = start;
+    loop {
+        let time = start.elapsed();
+        let positions = servos
+            .iter()
+            .map(|servo| servo.read_position(port))
+            .collect::<Result<Vec<Position>, ServoError>>()?;
+
+        let frame = Frame { time, positions };
+        let done = !keep_going(&frame);
+        recording.frames.push(frame);
+        if done {
+            break;
+        }
+
+        deadline += period;
+        sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+
+    Ok(recording)
+}
+
+/// Play a recording back through `servos`, which must have the recording's ids in the same
+/// order, writing each frame as a position goal at its recorded time.
+///
+/// Each goal is timed to the gap since the previous frame, so the servo moves smoothly between
+/// samples instead of jumping at full speed. Gaps longer than a goal time can hold, about 33
+/// seconds, are moved through in that time instead. The first frame is reached over `lead_in`,
+/// and the rest of the recording is delayed by it. A zero `lead_in` moves to the first frame at
+/// full speed.
+///
+/// Goals go through each servo's soft limits and status policy, as with [`Servo::write`].
+pub fn replay(
+    recording: &Recording,
+    servos: &[Servo],
+    lead_in: Duration,
+    port: &mut impl Port,
+) -> Result<(), RecordingError> {
+    let given: Vec<ID> = servos.iter().map(Servo::id).collect();
+    if given != recording.ids {
+        return Err(RecordingError::Servos {
+            recorded: recording.ids.clone(),
+            given,
+        });
+    }
+
+    let torque_on = Assign::new().with(Torque::Enabled);
+    for servo in servos {
+        servo.write(&torque_on, port)?;
+    }
+
+    let start = Instant::now();
+    let mut previous = Duration::ZERO;
+    for frame in &recording.frames {
+        let due = lead_in + frame.time;
+        sleep(due.saturating_sub(start.elapsed()));
+
+        let time = GoalTime::saturating(due.saturating_sub(previous));
+        previous = due;
+
+        for (servo, &position) in servos.iter().zip(&frame.positions) {
+            servo.write(&Assign::new_timed_position_goal(position, time), port)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::address,
+        servo::{LimitPolicy, SoftLimits},
+        sim::Simulator,
+    };
+
+    #[test]
+    fn load_rejects_times_going_backwards() {
+        let text = format!(
+            "{}\n1 2\n0 100 200\n50 110 210\n40 120 220\n",
+            Recording::HEADER
+        );
+        let result = Recording::load(text.as_bytes());
+        assert!(matches!(result, Err(RecordingError::Format(5))));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let text = format!("{}\n1 2\n0 100 200\n50 110 210\n", Recording::HEADER);
+        let recording = Recording::load(text.as_bytes()).unwrap();
+
+        let mut saved = Vec::new();
+        recording.save(&mut saved).unwrap();
+        assert_eq!(String::from_utf8(saved).unwrap(), text);
+    }
+
+    #[test]
+    fn replay_times_goals_to_the_frame_gaps() {
+        let text = format!("{}\n1\n0 2048\n20 2100\n50 2200\n", Recording::HEADER);
+        let recording = Recording::load(text.as_bytes()).unwrap();
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::new();
+        sim.add_servo(id);
+
+        replay(&recording, &[Servo::new(id)], Duration::ZERO, &mut sim).unwrap();
+
+        let servo = sim.servo(id).unwrap();
+        assert_eq!(servo.word(address::GoalPosition), 2200);
+        assert_eq!(servo.word(address::GoalTime), 30);
+    }
+
+    #[test]
+    fn replay_leads_in_to_the_first_frame() {
+        let text = format!("{}\n1\n0 3000\n", Recording::HEADER);
+        let recording = Recording::load(text.as_bytes()).unwrap();
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::new();
+        sim.add_servo(id);
+
+        let lead_in = Duration::from_millis(20);
+        replay(&recording, &[Servo::new(id)], lead_in, &mut sim).unwrap();
+
+        let servo = sim.servo(id).unwrap();
+        assert_eq!(servo.word(address::GoalPosition), 3000);
+        assert_eq!(servo.word(address::GoalTime), 20);
+    }
+
+    #[test]
+    fn replay_applies_the_servos_soft_limits() {
+        let text = format!("{}\n1\n0 3500\n", Recording::HEADER);
+        let recording = Recording::load(text.as_bytes()).unwrap();
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::new();
+        sim.add_servo(id);
+
+        let position = |steps| Position::new_raw(steps).unwrap();
+        let limits = SoftLimits::new(position(1000), position(3000), LimitPolicy::Clamp).unwrap();
+        let servo = Servo::new(id).with_limits(limits);
+        replay(&recording, &[servo], Duration::ZERO, &mut sim).unwrap();
+
+        let goal = sim.servo(id).unwrap().word(address::GoalPosition);
+        assert_eq!(goal, 3000);
+    }
+
+    #[test]
+    fn replay_needs_the_recorded_servos() {
+        let text = format!("{}\n1 2\n0 100 200\n", Recording::HEADER);
+        let recording = Recording::load(text.as_bytes()).unwrap();
+        let mut sim = Simulator::new();
+
+        let servos = [2, 1].map(|id| Servo::new(ID::single(id).unwrap()));
+        let result = replay(&recording, &servos, Duration::ZERO, &mut sim);
+        assert!(matches!(result, Err(RecordingError::Servos { .. })));
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, sleep, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bus::Bus,
+    hardware::{
+        address::{self, Address, ReadRegion},
+        decode_signed, DriverErrors, ID, LOAD_SIGN_BIT, SPEED_SIGN_BIT,
+    },
+    serial::Port,
+    servo::{self, Position, Servo, ServoError},
+};
+
+/// Everything read from one servo at one moment.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub id: ID,
+    /// When the last register was read.
+    pub time: Instant,
+    pub position: Position,
+    /// Signed, in steps per second.
+    pub speed: i32,
+    /// Signed, in 0.1% of the maximum.
+    pub load: i32,
+    /// In 0.1V.
+    pub voltage: u8,
+    /// In degrees celsius.
+    pub temperature: u8,
+    /// In 6.5mA.
+    pub current: u16,
+    pub moving: bool,
+    /// Status reported with any of the responses, including unknown bits. Statuses that fail
+    /// the servo's [`StatusPolicy`](crate::servo::StatusPolicy) fail the read instead.
+    pub errors: Option<DriverErrors>,
+}
+
+/// Read a full telemetry sample from one servo.
+pub fn read_sample(servo: &Servo, port: &mut impl Port) -> Result<Sample, ServoError> {
+    // Position through temperature are adjacent, so read them together.
+    let region = ReadRegion::new(address::PresentPosition.index(), 8)
+        .expect("Present position through temperature must be readable.");
+    let present = servo.read(region, port)?;
+    let moving = servo.read(ReadRegion::one(address::Moving), port)?;
+    let current = servo.read(ReadRegion::one(address::PresentCurrent), port)?;
+    let time = Instant::now();
+
+    let errors = [&present, &moving, &current]
+        .into_iter()
+        .find_map(|response| response.errors);
+
+    let present: [u8; 8] = servo::payload(&present)?;
+    let [moving] = servo::payload(&moving)?;
+    let current = servo::payload(&current)?;
+
+    Ok(Sample {
+        id: servo.id(),
+        time,
+        position: Position::new_raw(u16::from_le_bytes([present[0], present[1]]))?,
+        speed: decode_signed(u16::from_le_bytes([present[2], present[3]]), SPEED_SIGN_BIT),
+        load: decode_signed(u16::from_le_bytes([present[4], present[5]]), LOAD_SIGN_BIT),
+        voltage: present[6],
+        temperature: present[7],
+        current: u16::from_le_bytes(current),
+        moving: moving != 0,
+        errors,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryStats {
+    /// Complete passes over every servo.
+    pub cycles: u64,
+    /// Samples delivered to the subscriber.
+    pub samples: u64,
+    /// Samples read, but not delivered because the subscriber was not keeping up.
+    pub dropped: u64,
+    /// Samples that could not be read from the bus.
+    pub failed: u64,
+    /// Cycles that took longer than the target period.
+    pub overruns: u64,
+    /// Cycles per second since the poller started.
+    pub achieved_rate: f32,
+}
+
+/// Polls a set of servos in a background thread, publishing each [`Sample`].
+///
+/// The bus is locked for one servo at a time, so other users of the same [`Bus`] can send
+/// commands between reads. Polling stops when the poller is dropped.
+#[derive(Debug)]
+pub struct Telemetry {
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<TelemetryStats>>,
+    /// Taken once the thread has been joined.
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Telemetry {
+    /// Poll `servos` every `period`, publishing samples through a channel holding up to
+    /// `capacity` samples. Samples that do not fit are dropped.
+    pub fn spawn<P: Port + Send + 'static>(
+        bus: Bus<P>,
+        servos: Vec<Servo>,
+        period: Duration,
+        capacity: usize,
+    ) -> (Self, Receiver<Sample>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let telemetry = Self::spawn_with(bus, servos, period, move |sample| {
+            sender.try_send(sample).is_ok()
+        });
+
+        (telemetry, receiver)
+    }
+
+    /// Poll `servos` every `period`, passing each sample to `publish`, which returns whether it
+    /// was accepted.
+    pub fn spawn_with<P, F>(bus: Bus<P>, servos: Vec<Servo>, period: Duration, publish: F) -> Self
+    where
+        P: Port + Send + 'static,
+        F: FnMut(Sample) -> bool + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(Mutex::new(TelemetryStats::default()));
+
+        let handle = {
+            let stop = Arc::clone(&stop);
+            let stats = Arc::clone(&stats);
+            thread::spawn(move || poll(bus, servos, period, publish, stop, stats))
+        };
+
+        Self {
+            stop,
+            stats,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stats(&self) -> TelemetryStats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Stop polling, and wait for the current cycle to finish.
+    pub fn stop(mut self) -> TelemetryStats {
+        self.join();
+        self.stats()
+    }
+
+    fn join(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+fn poll<P: Port, F: FnMut(Sample) -> bool>(
+    bus: Bus<P>,
+    servos: Vec<Servo>,
+    period: Duration,
+    mut publish: F,
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<TelemetryStats>>,
+) {
+    let start = Instant::now();
+    let mut deadline = start;
+
+    while !stop.load(Ordering::Relaxed) {
+        for servo in &servos {
+            let sample = read_sample(servo, &mut *bus.lock());
+
+            let mut stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+            match sample.map(&mut publish) {
+                Err(_) => stats.failed += 1,
+                Ok(true) => stats.samples += 1,
+                Ok(false) => stats.dropped += 1,
+            }
+        }
+
+        let now = Instant::now();
+        deadline += period;
+
+        let mut stats = stats.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.cycles += 1;
+        stats.achieved_rate = stats.cycles as f32 / (now - start).as_secs_f32();
+        if now > deadline {
+            stats.overruns += 1;
+            deadline = now;
+        }
+        drop(stats);
+
+        sleep(deadline - now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::TryRecvError;
+
+    use super::*;
+    use crate::sim::Simulator;
+
+    fn bus_with(ids: &[u8]) -> (Bus<Simulator>, Vec<Servo>) {
+        let mut sim = Simulator::with_manual_clock();
+        let servos = ids
+            .iter()
+            .map(|&id| {
+                let id = ID::single(id).unwrap();
+                sim.add_servo(id);
+                Servo::new(id)
+            })
+            .collect();
+        (Bus::new(sim), servos)
+    }
+
+    #[test]
+    fn counts_delivered_dropped_and_failed_samples() {
+        let (bus, mut servos) = bus_with(&[1]);
+        // Nothing answers as servo 2.
+        servos.push(Servo::new(ID::single(2).unwrap()));
+
+        let (telemetry, receiver) = Telemetry::spawn(bus, servos, Duration::from_millis(5), 2);
+        sleep(Duration::from_millis(50));
+        let stats = telemetry.stop();
+
+        let samples: Vec<Sample> = receiver.try_iter().collect();
+        assert_eq!(samples.len(), 2);
+        assert!(samples.iter().all(|sample| sample.id.value() == 1));
+
+        assert!(stats.cycles >= 3, "Only {} cycles", stats.cycles);
+        assert_eq!(stats.samples, 2);
+        assert_eq!(stats.dropped, stats.cycles - 2);
+        assert_eq!(stats.failed, stats.cycles);
+    }
+
+    #[test]
+    fn reports_the_achieved_rate_and_overruns() {
+        let (bus, servos) = bus_with(&[1]);
+        let telemetry = Telemetry::spawn_with(bus, servos, Duration::from_millis(5), |_| {
+            sleep(Duration::from_millis(20));
+            true
+        });
+        sleep(Duration::from_millis(200));
+        let stats = telemetry.stop();
+
+        assert!(stats.cycles >= 3, "Only {} cycles", stats.cycles);
+        assert_eq!(stats.overruns, stats.cycles);
+        assert!(
+            (10.0..=50.0).contains(&stats.achieved_rate),
+            "Achieved {} cycles per second",
+            stats.achieved_rate
+        );
+    }
+
+    #[test]
+    fn dropping_stops_polling() {
+        let (bus, servos) = bus_with(&[1]);
+        let (sender, receiver) = mpsc::channel();
+        let telemetry =
+            Telemetry::spawn_with(bus, servos, Duration::from_millis(5), move |sample| {
+                sender.send(sample).is_ok()
+            });
+        sleep(Duration::from_millis(20));
+        drop(telemetry);
+
+        // The thread has finished, so the publisher and its sender are gone.
+        let _ = receiver.try_iter().count();
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(TryRecvError::Disconnected)
+        ));
+    }
+}
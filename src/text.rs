@@ -0,0 +1,23 @@
+use std::io::{self, BufRead};
+
+/// The lines of a text file after its `header`, each with its line number counting from 1,
+/// skipping blank lines. `None` when the file does not start with `header`.
+pub(crate) fn lines_after<R: BufRead>(
+    reader: R,
+    header: &str,
+) -> io::Result<Option<impl Iterator<Item = io::Result<(usize, String)>>>> {
+    let mut lines = reader.lines().zip(1..);
+
+    let Some((first, _)) = lines.next() else {
+        return Ok(None);
+    };
+    if first?.trim() != header {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        lines
+            .map(|(line, number)| line.map(|line| (number, line)))
+            .filter(|line| !matches!(line, Ok((_, line)) if line.trim().is_empty())),
+    ))
+}
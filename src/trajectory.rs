@@ -0,0 +1,416 @@
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{
+    serial::Port,
+    servo::{self, Assign, Position, Servo, ServoError},
+};
+
+/// Limits a planned move must stay within, in steps, steps per second, and so on.
+#[derive(Debug, Clone, Copy)]
+pub struct Constraints {
+    pub velocity: f32,
+    pub acceleration: f32,
+    /// Only used by jerk limited profiles.
+    pub jerk: f32,
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintError {
+    #[error("The velocity limit must be positive and finite.")]
+    Velocity,
+    #[error("The acceleration limit must be positive and finite.")]
+    Acceleration,
+    #[error("The jerk limit must be positive and finite.")]
+    Jerk,
+}
+
+impl Constraints {
+    fn validate(&self, jerk_limited: bool) -> Result<(), ConstraintError> {
+        let valid = |limit: f32| limit.is_finite() && limit > 0.0;
+        if !valid(self.velocity) {
+            return Err(ConstraintError::Velocity);
+        }
+        if !valid(self.acceleration) {
+            return Err(ConstraintError::Acceleration);
+        }
+        if jerk_limited && !valid(self.jerk) {
+            return Err(ConstraintError::Jerk);
+        }
+        Ok(())
+    }
+}
+
+/// A planned move from one position to another.
+pub trait Profile {
+    fn duration(&self) -> Duration;
+
+    /// The position at `time` after the start of the move, in steps.
+    fn sample(&self, time: Duration) -> f32;
+
+    fn start(&self) -> f32 {
+        self.sample(Duration::ZERO)
+    }
+
+    fn end(&self) -> f32 {
+        self.sample(self.duration())
+    }
+}
+
+/// The acceleration phase of a move, up to `peak` velocity. The deceleration phase mirrors it.
+#[derive(Debug, Clone, Copy)]
+struct Ramp {
+    peak: f32,
+    acceleration: f32,
+    jerk: f32,
+    /// Time spent changing acceleration at each end of the ramp.
+    jerk_time: f32,
+    /// Time spent at constant acceleration in the middle of the ramp.
+    constant_time: f32,
+}
+
+impl Ramp {
+    fn new(peak: f32, constraints: Constraints, jerk_limited: bool) -> Self {
+        if !jerk_limited {
+            return Self {
+                peak,
+                acceleration: constraints.acceleration,
+                jerk: 0.0,
+                jerk_time: 0.0,
+                constant_time: peak / constraints.acceleration,
+            };
+        }
+
+        let jerk = constraints.jerk;
+        if peak * jerk < constraints.acceleration.powi(2) {
+            // The peak is reached before acceleration saturates.
+            let jerk_time = (peak / jerk).sqrt();
+            Self {
+                peak,
+                acceleration: jerk * jerk_time,
+                jerk,
+                jerk_time,
+                constant_time: 0.0,
+            }
+        } else {
+            let jerk_time = constraints.acceleration / jerk;
+            Self {
+                peak,
+                acceleration: constraints.acceleration,
+                jerk,
+                jerk_time,
+                constant_time: peak / constraints.acceleration - jerk_time,
+            }
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        2.0 * self.jerk_time + self.constant_time
+    }
+
+    fn distance(&self) -> f32 {
+        self.peak * self.duration() / 2.0
+    }
+
+    /// Distance covered `t` seconds into the ramp.
+    fn sample(&self, t: f32) -> f32 {
+        let (j, a, tj, tc) = (
+            self.jerk,
+            self.acceleration,
+            self.jerk_time,
+            self.constant_time,
+        );
+
+        // Acceleration rising at the jerk limit.
+        let t1 = t.min(tj);
+        let mut s = j * t1.powi(3) / 6.0;
+        let mut v = j * t1.powi(2) / 2.0;
+        if t <= tj {
+            return s;
+        }
+
+        // Constant acceleration.
+        let t2 = (t - tj).min(tc);
+        s += v * t2 + a * t2.powi(2) / 2.0;
+        v += a * t2;
+        if t <= tj + tc {
+            return s;
+        }
+
+        // Acceleration falling at the jerk limit.
+        let t3 = (t - tj - tc).min(tj);
+        s + v * t3 + a * t3.powi(2) / 2.0 - j * t3.powi(3) / 6.0
+    }
+}
+
+/// A move with symmetric acceleration and deceleration ramps around a constant velocity.
+#[derive(Debug, Clone, Copy)]
+struct Plan {
+    start: f32,
+    end: f32,
+    ramp: Ramp,
+    cruise_time: f32,
+}
+
+impl Plan {
+    fn new(start: Position, end: Position, constraints: Constraints, jerk_limited: bool) -> Self {
+        let start = start.value() as f32;
+        let end = end.value() as f32;
+        let distance = (end - start).abs();
+
+        let mut ramp = Ramp::new(constraints.velocity, constraints, jerk_limited);
+        if 2.0 * ramp.distance() > distance {
+            // The move is too short to reach full velocity, so search for the peak it can reach.
+            let (mut low, mut high) = (0.0, constraints.velocity);
+            for _ in 0..64 {
+                let peak = (low + high) / 2.0;
+                if 2.0 * Ramp::new(peak, constraints, jerk_limited).distance() > distance {
+                    high = peak;
+                } else {
+                    low = peak;
+                }
+            }
+            ramp = Ramp::new(low, constraints, jerk_limited);
+        }
+
+        let cruise_time = if ramp.peak > 0.0 {
+            (distance - 2.0 * ramp.distance()).max(0.0) / ramp.peak
+        } else {
+            0.0
+        };
+
+        Self {
+            start,
+            end,
+            ramp,
+            cruise_time,
+        }
+    }
+
+    fn total(&self) -> f32 {
+        2.0 * self.ramp.duration() + self.cruise_time
+    }
+
+    fn sample(&self, time: Duration) -> f32 {
+        let t = time.as_secs_f32().min(self.total());
+        let distance = (self.end - self.start).abs();
+        let ramp = self.ramp.duration();
+
+        let travelled = if t <= ramp {
+            self.ramp.sample(t)
+        } else if t <= ramp + self.cruise_time {
+            self.ramp.distance() + self.ramp.peak * (t - ramp)
+        } else {
+            distance - self.ramp.sample(self.total() - t)
+        };
+
+        self.start + travelled.clamp(0.0, distance) * (self.end - self.start).signum()
+    }
+}
+
+/// Constant acceleration up to the velocity limit, cruise, then constant deceleration.
+#[derive(Debug, Clone, Copy)]
+pub struct Trapezoidal(Plan);
+
+impl Trapezoidal {
+    /// `constraints.jerk` is ignored.
+    pub fn new(
+        start: Position,
+        end: Position,
+        constraints: Constraints,
+    ) -> Result<Self, ConstraintError> {
+        constraints.validate(false)?;
+        Ok(Self(Plan::new(start, end, constraints, false)))
+    }
+}
+
+impl Profile for Trapezoidal {
+    fn duration(&self) -> Duration {
+        Duration::from_secs_f32(self.0.total())
+    }
+
+    fn sample(&self, time: Duration) -> f32 {
+        self.0.sample(time)
+    }
+}
+
+/// Like [`Trapezoidal`], but the acceleration itself ramps at the jerk limit, avoiding the
+/// sudden changes in force at the corners of a trapezoid.
+#[derive(Debug, Clone, Copy)]
+pub struct SCurve(Plan);
+
+impl SCurve {
+    pub fn new(
+        start: Position,
+        end: Position,
+        constraints: Constraints,
+    ) -> Result<Self, ConstraintError> {
+        constraints.validate(true)?;
+        Ok(Self(Plan::new(start, end, constraints, true)))
+    }
+}
+
+impl Profile for SCurve {
+    fn duration(&self) -> Duration {
+        Duration::from_secs_f32(self.0.total())
+    }
+
+    fn sample(&self, time: Duration) -> f32 {
+        self.0.sample(time)
+    }
+}
+
+/// The setpoints that would be streamed for a profile, one every `period`, including the end.
+pub fn samples<P: Profile + ?Sized>(profile: &P, period: Duration) -> Vec<(Duration, Position)> {
+    assert!(!period.is_zero(), "Sample period must not be zero.");
+    let duration = profile.duration();
+
+    let mut samples: Vec<(Duration, Position)> = (0..)
+        .map(|index| period * index)
+        .take_while(|&time| time < duration)
+        .map(|time| (time, Position::from_steps(profile.sample(time))))
+        .collect();
+    samples.push((duration, Position::from_steps(profile.end())));
+
+    samples
+}
+
+/// Stream each servo's profile as `GoalPosition` setpoints, one every `period`, until every
+/// profile has finished. Each tick is sent as one sync write.
+pub fn stream(
+    moves: &[(&Servo, &dyn Profile)],
+    period: Duration,
+    port: &mut impl Port,
+) -> Result<(), ServoError> {
+    let duration = moves
+        .iter()
+        .map(|(_, profile)| profile.duration())
+        .max()
+        .unwrap_or(Duration::ZERO);
+
+    let start = Instant::now();
+    let mut deadline = start;
+    loop {
+        let time = start.elapsed().min(duration);
+        let setpoints: Vec<Assign> = moves
+            .iter()
+            .map(|(_, profile)| Assign::new().with(Position::from_steps(profile.sample(time))))
+            .collect();
+        let writes: Vec<(&Servo, &Assign)> = moves
+            .iter()
+            .map(|&(servo, _)| servo)
+            .zip(&setpoints)
+            .collect();
+        servo::sync_write(&writes, port)?;
+
+        if time >= duration {
+            break;
+        }
+
+        deadline += period;
+        sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hardware::ID, sim::Simulator};
+
+    const CONSTRAINTS: Constraints = Constraints {
+        velocity: 2000.0,
+        acceleration: 4000.0,
+        jerk: 20000.0,
+    };
+
+    fn position(steps: u16) -> Position {
+        Position::new_raw(steps).unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_constraints() {
+        let (start, end) = (position(0), position(4000));
+        let with = |velocity, acceleration, jerk| Constraints {
+            velocity,
+            acceleration,
+            jerk,
+        };
+
+        assert_eq!(
+            Trapezoidal::new(start, end, with(0.0, 4000.0, 0.0)).err(),
+            Some(ConstraintError::Velocity)
+        );
+        assert_eq!(
+            Trapezoidal::new(start, end, with(2000.0, 0.0, 0.0)).err(),
+            Some(ConstraintError::Acceleration)
+        );
+        assert_eq!(
+            SCurve::new(start, end, with(f32::NAN, 4000.0, 20000.0)).err(),
+            Some(ConstraintError::Velocity)
+        );
+        assert_eq!(
+            SCurve::new(start, end, with(2000.0, -1.0, 20000.0)).err(),
+            Some(ConstraintError::Acceleration)
+        );
+        assert_eq!(
+            SCurve::new(start, end, with(2000.0, 4000.0, 0.0)).err(),
+            Some(ConstraintError::Jerk)
+        );
+        assert!(Trapezoidal::new(start, end, with(2000.0, 4000.0, 0.0)).is_ok());
+    }
+
+    /// Send each setpoint to a simulated servo, returning where it was after each period.
+    fn follow(profile: &dyn Profile, period: Duration) -> Vec<u16> {
+        let id = ID::single(1).unwrap();
+        let servo = Servo::new(id);
+        let mut sim = Simulator::with_manual_clock();
+        sim.add_servo(id).set_position(profile.start());
+
+        samples(profile, period)
+            .into_iter()
+            .map(|(_, setpoint)| {
+                servo::sync_write(&[(&servo, &Assign::new().with(setpoint))], &mut sim).unwrap();
+                sim.step(period);
+                sim.servo(id).unwrap().position().round() as u16
+            })
+            .collect()
+    }
+
+    fn check_path(profile: &dyn Profile, start: u16, end: u16) {
+        let period = Duration::from_millis(10);
+        let path = follow(profile, period);
+
+        assert_eq!(path.last(), Some(&end));
+        let direction = (end as i32 - start as i32).signum();
+        let limit = CONSTRAINTS.velocity * period.as_secs_f32() + 1.0;
+        for pair in path.windows(2) {
+            let step = pair[1] as i32 - pair[0] as i32;
+            assert!(step * direction >= 0, "The path must not reverse: {path:?}");
+            assert!(step.abs() as f32 <= limit, "The path is too fast: {path:?}");
+        }
+    }
+
+    #[test]
+    fn trapezoidal_path_reaches_target() {
+        let profile = Trapezoidal::new(position(500), position(3500), CONSTRAINTS).unwrap();
+        assert_eq!(profile.start(), 500.0);
+        assert_eq!(profile.end(), 3500.0);
+        check_path(&profile, 500, 3500);
+    }
+
+    #[test]
+    fn s_curve_path_reaches_target() {
+        let profile = SCurve::new(position(3000), position(1000), CONSTRAINTS).unwrap();
+        check_path(&profile, 3000, 1000);
+
+        // Short moves never reach the velocity limit, but must still arrive.
+        let profile = SCurve::new(position(2000), position(2100), CONSTRAINTS).unwrap();
+        check_path(&profile, 2000, 2100);
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{
+    hardware::{
+        address::{self, Address, ReadRegion},
+        decode_signed, DriverErrors, ID, LOAD_SIGN_BIT,
+    },
+    serial::Port,
+    servo::{self, Position, Servo, ServoError, StatusPolicy, STEPS_PER_TURN},
+};
+
+#[derive(Debug, Error)]
+pub enum WaitError {
+    #[error("Servo Error: {0}")]
+    Servo(#[from] ServoError),
+    #[error("Servo {id:?} did not reach its goal in time, stopping at {position:?}.")]
+    Timeout { id: ID, position: Position },
+    #[error("Servo {id:?} stalled at {position:?} under load {load}.")]
+    Stall {
+        id: ID,
+        position: Position,
+        load: i32,
+    },
+    #[error("Servo {id:?} reported {errors}")]
+    Driver { id: ID, errors: DriverErrors },
+}
+
+#[derive(Debug, Clone)]
+pub struct WaitConfig {
+    /// How close to the goal counts as reached, in steps.
+    pub tolerance: u16,
+    pub timeout: Duration,
+    /// Time between polls when blocking.
+    pub poll_period: Duration,
+    /// Load, in 0.1% of the maximum, above which a servo that is not moving counts as stalled.
+    pub stall_load: u16,
+    /// How long a servo must stay stalled before giving up.
+    pub stall_time: Duration,
+    /// Which status bits fail the wait, on top of the servo's own [`StatusPolicy`].
+    /// [`StatusPolicy::Ignore`] leaves it to the servo's policy alone.
+    pub driver_status: StatusPolicy,
+}
+
+impl Default for WaitConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 10,
+            timeout: Duration::from_secs(5),
+            poll_period: Duration::from_millis(10),
+            stall_load: 500,
+            stall_time: Duration::from_millis(200),
+            driver_status: StatusPolicy::FailOnKnown,
+        }
+    }
+}
+
+/// Waits for a servo to stop at its goal, one check at a time.
+///
+/// Each call to [`Waiter::check`] reads the servo once and never sleeps, so checks can be
+/// interleaved with other work in a custom loop. Reads still block on the port, so this is not
+/// a future. [`Servo::wait_until_stopped`] drives it by sleeping between checks.
+///
+/// A status byte fails the wait when either [`WaitConfig::driver_status`] or the servo's own
+/// [`StatusPolicy`] fails on it. By default, any known driver error does.
+#[derive(Debug)]
+pub struct Waiter {
+    servo: Servo,
+    goal: Position,
+    config: WaitConfig,
+    start: Instant,
+    stalled_since: Option<Instant>,
+}
+
+impl Waiter {
+    pub fn new(servo: Servo, goal: Position, config: WaitConfig) -> Self {
+        Self {
+            servo,
+            goal,
+            config,
+            start: Instant::now(),
+            stalled_since: None,
+        }
+    }
+
+    /// Read the servo once, returning its position when it has stopped at the goal, or `None`
+    /// while it is still on its way.
+    pub fn check(&mut self, port: &mut impl Port) -> Result<Option<Position>, WaitError> {
+        let id = self.servo.id();
+
+        // Position, speed and load are adjacent, so read them together.
+        let region = ReadRegion::new(address::PresentPosition.index(), 6)
+            .expect("Present position, speed and load must be readable.");
+        let [position_l, position_h, _, _, load_l, load_h] = self.read(region, port)?;
+        let [moving] = self.read(ReadRegion::one(address::Moving), port)?;
+
+        let position = Position::new_raw(u16::from_le_bytes([position_l, position_h]))
+            .map_err(ServoError::from)?;
+        let load = decode_signed(u16::from_le_bytes([load_l, load_h]), LOAD_SIGN_BIT);
+        let moving = moving != 0;
+
+        // The goal may be just across the rollover from the present position.
+        let distance = position.value().abs_diff(self.goal.value());
+        let distance = distance.min(STEPS_PER_TURN - distance);
+        let reached = distance <= self.config.tolerance;
+        if reached && !moving {
+            return Ok(Some(position));
+        }
+
+        let now = Instant::now();
+        if !moving && load.unsigned_abs() >= self.config.stall_load as u32 {
+            let since = *self.stalled_since.get_or_insert(now);
+            if now - since >= self.config.stall_time {
+                return Err(WaitError::Stall { id, position, load });
+            }
+        } else {
+            self.stalled_since = None;
+        }
+
+        if now - self.start >= self.config.timeout {
+            return Err(WaitError::Timeout { id, position });
+        }
+
+        Ok(None)
+    }
+
+    fn read<const N: usize>(
+        &self,
+        region: ReadRegion,
+        port: &mut impl Port,
+    ) -> Result<[u8; N], WaitError> {
+        let id = self.servo.id();
+        let response = self.servo.read(region, port).map_err(|e| match e {
+            ServoError::Driver(errors) => WaitError::Driver { id, errors },
+            e => WaitError::Servo(e),
+        })?;
+
+        match response.errors {
+            Some(errors) if self.config.driver_status.fails(errors) => {
+                Err(WaitError::Driver { id, errors })
+            }
+            _ => Ok(servo::payload(&response)?),
+        }
+    }
+}
+
+/// Block until every servo has stopped within `config.tolerance` of its goal, returning their
+/// final positions. Fails as soon as any one of them times out, stalls or reports an error.
+pub fn wait_all_until_stopped(
+    goals: &[(&Servo, Position)],
+    config: &WaitConfig,
+    port: &mut impl Port,
+) -> Result<Vec<Position>, WaitError> {
+    let mut waiters: Vec<Waiter> = goals
+        .iter()
+        .map(|&(servo, goal)| Waiter::new(servo.clone(), goal, config.clone()))
+        .collect();
+    let mut positions: Vec<Option<Position>> = vec![None; goals.len()];
+
+    loop {
+        for (waiter, position) in waiters.iter_mut().zip(positions.iter_mut()) {
+            if position.is_none() {
+                *position = waiter.check(port)?;
+            }
+        }
+
+        if let Some(positions) = positions.iter().copied().collect::<Option<Vec<Position>>>() {
+            return Ok(positions);
+        }
+
+        sleep(config.poll_period);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hardware::DriverError,
+        servo::{Assign, Torque},
+        sim::Simulator,
+    };
+
+    const UNKNOWN_STATUS: u8 = 0x40;
+
+    fn stopped_at_goal(status: u8) -> (Simulator, ID, Position) {
+        let id = ID::single(1).unwrap();
+        let mut sim = Simulator::with_manual_clock();
+        let servo = sim.add_servo(id);
+        servo.set_errors(status);
+        let goal = Position::new_raw(servo.position().round() as u16).unwrap();
+        (sim, id, goal)
+    }
+
+    fn ignoring_status() -> WaitConfig {
+        WaitConfig {
+            driver_status: StatusPolicy::Ignore,
+            ..WaitConfig::default()
+        }
+    }
+
+    #[test]
+    fn known_status_fails_by_default() {
+        let overload = DriverError::OverLoad as u8;
+        let (mut sim, id, goal) = stopped_at_goal(overload);
+
+        let result = Servo::new(id).wait_until_stopped(goal, &WaitConfig::default(), &mut sim);
+        assert!(
+            matches!(result, Err(WaitError::Driver { errors, .. }) if errors.raw() == overload)
+        );
+
+        let result = Servo::new(id).wait_until_stopped(goal, &ignoring_status(), &mut sim);
+        assert_eq!(result.unwrap().value(), goal.value());
+    }
+
+    #[test]
+    fn unknown_status_follows_the_policies() {
+        let (mut sim, id, goal) = stopped_at_goal(UNKNOWN_STATUS);
+
+        let servo = Servo::new(id);
+        let result = servo.wait_until_stopped(goal, &WaitConfig::default(), &mut sim);
+        assert_eq!(result.unwrap().value(), goal.value());
+
+        let config = WaitConfig {
+            driver_status: StatusPolicy::FailOnAny,
+            ..WaitConfig::default()
+        };
+        let result = servo.wait_until_stopped(goal, &config, &mut sim);
+        assert!(
+            matches!(result, Err(WaitError::Driver { errors, .. }) if errors.raw() == UNKNOWN_STATUS)
+        );
+    }
+
+    #[test]
+    fn the_servos_policy_also_fails_the_wait() {
+        let (mut sim, id, goal) = stopped_at_goal(UNKNOWN_STATUS);
+
+        let servo = Servo::new(id).with_status_policy(StatusPolicy::FailOnAny);
+        let result = servo.wait_until_stopped(goal, &ignoring_status(), &mut sim);
+        assert!(
+            matches!(result, Err(WaitError::Driver { errors, .. }) if errors.raw() == UNKNOWN_STATUS)
+        );
+    }
+
+    #[test]
+    fn times_out_short_of_the_goal() {
+        let (mut sim, id, _) = stopped_at_goal(0);
+        let config = WaitConfig {
+            timeout: Duration::from_millis(50),
+            ..WaitConfig::default()
+        };
+
+        let goal = Position::new_raw(3000).unwrap();
+        let result = Servo::new(id).wait_until_stopped(goal, &config, &mut sim);
+        assert!(matches!(
+            result,
+            Err(WaitError::Timeout { position, .. }) if position.value() == 2048
+        ));
+    }
+
+    #[test]
+    fn stalls_under_load() {
+        let (mut sim, id, _) = stopped_at_goal(0);
+        sim.servo_mut(id).unwrap().set_load(-600);
+        let config = WaitConfig {
+            stall_time: Duration::from_millis(30),
+            ..WaitConfig::default()
+        };
+
+        let goal = Position::new_raw(3000).unwrap();
+        let result = Servo::new(id).wait_until_stopped(goal, &config, &mut sim);
+        assert!(matches!(result, Err(WaitError::Stall { load: -600, .. })));
+    }
+
+    #[test]
+    fn light_loads_do_not_stall() {
+        let (mut sim, id, _) = stopped_at_goal(0);
+        sim.servo_mut(id).unwrap().set_load(400);
+        let config = WaitConfig {
+            timeout: Duration::from_millis(50),
+            stall_time: Duration::from_millis(10),
+            ..WaitConfig::default()
+        };
+
+        let goal = Position::new_raw(3000).unwrap();
+        let result = Servo::new(id).wait_until_stopped(goal, &config, &mut sim);
+        assert!(matches!(result, Err(WaitError::Timeout { .. })));
+    }
+
+    #[test]
+    fn reaches_goals_across_the_rollover() {
+        let (mut sim, id, _) = stopped_at_goal(0);
+        let servo = Servo::new(id);
+        servo
+            .write(&Assign::new().with(Torque::Disabled), &mut sim)
+            .unwrap();
+        sim.servo_mut(id).unwrap().set_position(2.0);
+
+        let goal = Position::new_raw(4094).unwrap();
+        let result = servo.wait_until_stopped(goal, &WaitConfig::default(), &mut sim);
+        assert_eq!(result.unwrap().value(), 2);
+    }
+}
@@ -0,0 +1,55 @@
+//! Replay every input in the fuzz corpus, so inputs that once crashed a fuzz target stay
+//! fixed without running the fuzzer.
+
+use std::{fs, path::PathBuf};
+
+use arbitrary::{Arbitrary, Unstructured};
+
+#[path = "../fuzz/checks/assign.rs"]
+mod assign;
+#[path = "../fuzz/checks/regions.rs"]
+mod regions;
+#[path = "../fuzz/checks/response.rs"]
+mod response;
+
+fn corpus(target: &str) -> Vec<Vec<u8>> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus")
+        .join(target);
+    let inputs: Vec<Vec<u8>> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Corpus {} must be readable: {e}", dir.display()))
+        .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+        .collect();
+    assert!(!inputs.is_empty(), "Corpus {} is empty.", dir.display());
+    inputs
+}
+
+/// Decode an input the way `fuzz_target!` does, skipping bytes it would also skip.
+fn decode<'a, T: Arbitrary<'a>>(data: &'a [u8]) -> Option<T> {
+    T::arbitrary_take_rest(Unstructured::new(data)).ok()
+}
+
+#[test]
+fn response_corpus() {
+    for data in corpus("response") {
+        response::check(&data);
+    }
+}
+
+#[test]
+fn regions_corpus() {
+    for data in corpus("regions") {
+        if let Some(input) = decode(&data) {
+            regions::check(input);
+        }
+    }
+}
+
+#[test]
+fn assign_corpus() {
+    for data in corpus("assign") {
+        if let Some(input) = decode(&data) {
+            assign::check(input);
+        }
+    }
+}